@@ -1,6 +1,7 @@
 //! ast.rs - 定义抽象语法树（AST）的节点。
 //! AST 是解析器将源代码的语法结构进行模型化的方式。
 
+use std::cell::Cell;
 use std::fmt::format;
 use std::rc::Rc;
 use crate::token::{Literal, Token};
@@ -27,25 +28,118 @@ pub enum Expr {
     Literal {
         value: Token,
     },
-    /// 变量访问表达式，例如 `x` 或 `myVariable`
+    /// 变量访问表达式，例如 `x` 或 `myVariable`。
+    /// `depth` 由 resolver 填写：变量声明所在的作用域与当前作用域相隔的层数，None 表示全局变量
     Variable {
         name: Token,
+        depth: Cell<Option<usize>>,
     },
     Assign {
         name: Token,  // 被赋值的变量标识符
         value: Box<Expr>,
+        depth: Cell<Option<usize>>, // 含义与 Variable 的 depth 相同
+    },
+    /// `x min= v` / `a[i] max= v`：把目标更新为它与右侧值中较小（较大）的一个。
+    /// `operator` 是 `min` 或 `max` 标识符；`target` 是 `Variable` 或 `Index`，只求值一次
+    MinMaxAssign {
+        target: Box<Expr>,
+        operator: Token,
+        value: Box<Expr>,
     },
     Logical {
         left: Box<Expr>,
         operator: Token, // 逻辑运算符，例如 `and`, `or`
         right: Box<Expr>,
     },
+    /// 条件表达式 `cond ? a : b`，只求值被选中的分支
+    Ternary {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+    },
+    /// if 表达式 `if (cond) a else b`，两个分支都必须存在，只求值被选中的分支
+    IfExpr {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+    },
     Call {
         callee: Box<Expr>,
         paren: Token,
         arguments: Vec<Expr>,
     },
-
+    /// 函数字面量，求值时捕获当前环境生成一个闭包。
+    /// 由匿名函数 `fun (x) { ... }` 或尾随块语法 `f(x) { ... }` 产生
+    Function {
+        keyword: Token,
+        params: Vec<Token>,
+        /// 剩余参数 `...name`，收集多出来的实参
+        rest: Option<Token>,
+        body: Rc<Stmt>,
+    },
+    /// 带标签的块表达式，例如 `outer: { ...; break outer 42; }`，
+    /// 其值为 `break outer value` 携带的值，正常执行完毕时为 nil
+    LabeledBlock {
+        label: Token,
+        statements: Vec<Stmt>,
+    },
+    /// 括号内以逗号分隔的表达式列表，例如 `(b, a)`，目前只用于并行赋值
+    Tuple {
+        paren: Token,
+        elements: Vec<Expr>,
+    },
+    /// 列表字面量，例如 `[1, 2, 3]`
+    ListLiteral {
+        bracket: Token,
+        elements: Vec<Expr>,
+    },
+    /// 下标访问，例如 `a[0]`
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+    },
+    /// 下标赋值，例如 `a[0] = 5`
+    SetIndex {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+    },
+    /// 调用实参中的展开 `f(...list)`，列表的每个元素都作为单独的实参传入
+    Spread {
+        ellipsis: Token,
+        expression: Box<Expr>,
+    },
+    /// 方法中的 `this`，指向调用方法的实例。`depth` 的含义与 `Variable` 相同
+    This {
+        keyword: Token,
+        depth: Cell<Option<usize>>,
+    },
+    /// `super.method`，在超类中查找方法并绑定到当前的 `this` 上。`depth` 是 `super` 所在环境的距离
+    Super {
+        keyword: Token,
+        method: Token,
+        depth: Cell<Option<usize>>,
+    },
+    /// 属性访问，例如 `point.x`
+    Get {
+        object: Box<Expr>,
+        name: Token,
+    },
+    /// 属性赋值，例如 `point.x = 1`
+    Set {
+        object: Box<Expr>,
+        name: Token,
+        value: Box<Expr>,
+    },
+    /// 并行赋值，例如 `(a, b) = (b, a)`：先求出右侧全部的值，再依次绑定
+    MultiAssign {
+        names: Vec<Token>,
+        depths: Vec<Cell<Option<usize>>>, // 与 names 一一对应，含义与 Variable 的 depth 相同
+        equals: Token,
+        value: Box<Expr>,
+    },
 }
 
 impl Expr {
@@ -76,6 +170,7 @@ impl Expr {
                                 format!("{}", n)
                             }
                         }
+                        Literal::Integer(n) => format!("{}.0", n),
                         Literal::String(s) => s.clone(),
                     }
                 } else {
@@ -83,12 +178,15 @@ impl Expr {
                     value.lexeme.clone()
                 }
             }
-            Expr::Variable { name } => {
+            Expr::Variable { name, .. } => {
                 name.lexeme.clone()
             }
-            Expr::Assign { name, value } => {
+            Expr::Assign { name, value, .. } => {
                 format!("(assign {} {})", name.lexeme, value.to_string_expr())
             }
+            Expr::MinMaxAssign { target, operator, value } => {
+                format!("({}= {} {})", operator.lexeme, target.to_string_expr(), value.to_string_expr())
+            }
             Expr::Logical { left, operator, right } => {
                 format!(
                     "({} {} {})",
@@ -96,6 +194,20 @@ impl Expr {
                     operator.lexeme,
                     right.to_string_expr())
             }
+            Expr::Ternary { condition, then_branch, else_branch } => {
+                format!(
+                    "(? {} {} {})",
+                    condition.to_string_expr(),
+                    then_branch.to_string_expr(),
+                    else_branch.to_string_expr())
+            }
+            Expr::IfExpr { condition, then_branch, else_branch } => {
+                format!(
+                    "(if {} {} {})",
+                    condition.to_string_expr(),
+                    then_branch.to_string_expr(),
+                    else_branch.to_string_expr())
+            }
             Expr::Call { callee,arguments, .. } => {
                 format!(
                     "(call {} {})",
@@ -106,6 +218,70 @@ impl Expr {
                         .join(" ")
                 )
             }
+            Expr::Function { params, rest, .. } => {
+                format!(
+                    "(lambda ({}))",
+                    params.iter()
+                        .map(|p| p.lexeme.clone())
+                        .chain(rest.iter().map(|r| format!("...{}", r.lexeme)))
+                        .collect::<Vec<String>>()
+                        .join(" ")
+                )
+            }
+            Expr::LabeledBlock { label, .. } => {
+                format!("(block {})", label.lexeme)
+            }
+            Expr::Tuple { elements, .. } => {
+                format!(
+                    "(tuple {})",
+                    elements.iter()
+                        .map(|e| e.to_string_expr())
+                        .collect::<Vec<String>>()
+                        .join(" ")
+                )
+            }
+            Expr::ListLiteral { elements, .. } => {
+                format!(
+                    "(list {})",
+                    elements.iter()
+                        .map(|e| e.to_string_expr())
+                        .collect::<Vec<String>>()
+                        .join(" ")
+                )
+            }
+            Expr::Index { object, index, .. } => {
+                format!("(index {} {})", object.to_string_expr(), index.to_string_expr())
+            }
+            Expr::SetIndex { object, index, value, .. } => {
+                format!(
+                    "(set-index {} {} {})",
+                    object.to_string_expr(),
+                    index.to_string_expr(),
+                    value.to_string_expr())
+            }
+            Expr::Spread { expression, .. } => format!("(... {})", expression.to_string_expr()),
+            Expr::This { .. } => "this".to_string(),
+            Expr::Super { method, .. } => format!("(super {})", method.lexeme),
+            Expr::Get { object, name } => {
+                format!("(get {} {})", object.to_string_expr(), name.lexeme)
+            }
+            Expr::Set { object, name, value } => {
+                format!(
+                    "(set {} {} {})",
+                    object.to_string_expr(),
+                    name.lexeme,
+                    value.to_string_expr())
+            }
+            Expr::MultiAssign { names, value, .. } => {
+                format!(
+                    "(assign ({}) {})",
+                    names.iter()
+                        .map(|n| n.lexeme.clone())
+                        .collect::<Vec<String>>()
+                        .join(" "),
+                    value.to_string_expr()
+                )
+            }
         }
     }
 }
@@ -131,11 +307,22 @@ pub enum Stmt{
         then_branch: Box<Stmt>,
         else_branch: Option<Box<Stmt>>,
     },
+    /// while 循环。`while (var x = expr)` 形式会在每次迭代时把条件的值绑定到 `binding`
     While {
         name: Token,
+        binding: Option<Token>,
         condition: Expr,
         body: Box<Stmt>,
     },
+    /// 遍历列表或字符串的 `for (var x in expr) body`，每次迭代都在新的作用域中绑定 `var_name`。
+    /// `for (var i, x in expr)` 形式还会把从 0 开始的下标绑定到 `index_name`
+    ForEach {
+        name: Token,
+        index_name: Option<Token>,
+        var_name: Token,
+        iterable: Expr,
+        body: Box<Stmt>,
+    },
     For {
         name: Token,
         initializer: Option<Box<Stmt>>,
@@ -143,19 +330,78 @@ pub enum Stmt{
         increment: Option<Box<Stmt>>,
         body: Box<Stmt>,
     },
+    /// `break;`，或跳出带标签块的 `break label value;`
     Break {
         name: Token,
+        label: Option<Token>,
+        value: Option<Expr>,
     },
     Continue {
         name: Token,
     },
+    /// 函数声明。`rest` 是可选的剩余参数 `...name`，调用时多出来的实参会收集成列表绑定到它
     Function {
         name: Token,
         params: Vec<Token>,
+        rest: Option<Token>,
         body: Rc<Stmt>,
     },
     Return {
         name: Token,
         value: Option<Expr>,
     },
-}
\ No newline at end of file
+    /// 类声明，例如 `class Point < Shape { init(x) { ... } }`，`methods` 中都是 `Stmt::Function`。
+    /// `superclass` 是一个 `Expr::Variable`；`statics` 是用 `static` 修饰的成员，
+    /// 其中静态方法是 `Stmt::Function`，静态字段是 `Stmt::Var`
+    Class {
+        name: Token,
+        superclass: Option<Expr>,
+        methods: Vec<Stmt>,
+        statics: Vec<Stmt>,
+    },
+    /// 资源语句 `using (var r = expr) body`：在新的作用域中把资源绑定到 `var_name` 并执行 `body`，
+    /// 无论 `body` 正常结束、出错还是通过 return/break 离开，之后都会调用 `r.close()`
+    Using {
+        name: Token,
+        var_name: Token,
+        initializer: Expr,
+        body: Box<Stmt>,
+    },
+    /// 模式匹配语句，例如 `match x { 1 => print "one"; n => print n; }`
+    Match {
+        name: Token,
+        subject: Expr,
+        arms: Vec<MatchArm>,
+    },
+}
+
+/// match 语句中的一个分支：`pattern => body`
+#[derive(Debug)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Stmt,
+}
+
+/// match 分支可以使用的模式
+#[derive(Debug)]
+pub enum Pattern {
+    /// 字面量模式，例如 `1`、`"a"`、`true`、`nil`，与被匹配的值相等时匹配成功
+    Literal(Token),
+    /// 通配符 `_`，匹配任何值
+    Wildcard,
+    /// 绑定模式，匹配任何值，并在分支的作用域中把该值绑定到这个名字上
+    Binding(Token),
+    /// 列表模式，例如 `[1, x]`：匹配长度相同且每个元素都匹配对应子模式的列表
+    List(Vec<Pattern>),
+}
+
+impl Pattern {
+    /// 按从左到右的顺序返回模式中所有绑定的名字。
+    pub fn bindings(&self) -> Vec<&Token> {
+        match self {
+            Pattern::Binding(name) => vec![name],
+            Pattern::List(elements) => elements.iter().flat_map(Pattern::bindings).collect(),
+            Pattern::Literal(_) | Pattern::Wildcard => Vec::new(),
+        }
+    }
+}
@@ -3,5 +3,13 @@ use crate::msk_value::MskValue;
 
 pub trait Callable {
     fn arity(&self) -> usize;
+    /// 最少需要的参数个数，默认等于 `arity()`。
+    fn min_arity(&self) -> usize {
+        self.arity()
+    }
+    /// 最多接受的参数个数，`None` 表示不限。默认等于 `arity()`。
+    fn max_arity(&self) -> Option<usize> {
+        Some(self.arity())
+    }
     fn call(&self, interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError>;
 }
@@ -0,0 +1,88 @@
+//! class.rs - 类与实例的运行时表示。
+//! 类声明求值为一个 `MskClass`，调用类会创建 `MskInstance` 并执行 `init` 方法。
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::callable::Callable;
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::msk_value::MskValue;
+use crate::user_fun::UserFunction;
+
+pub struct MskClass {
+    pub name: String,
+    pub superclass: Option<Rc<MskClass>>,
+    pub methods: HashMap<String, Rc<UserFunction>>,
+    /// 静态方法和静态字段，通过类本身访问，可以在运行时修改
+    pub statics: RefCell<HashMap<String, MskValue>>,
+}
+
+impl MskClass {
+    /// 按名字查找方法，本类没有时沿继承链向上查找。
+    pub fn find_method(&self, name: &str) -> Option<Rc<UserFunction>> {
+        if let Some(method) = self.methods.get(name) {
+            return Some(method.clone());
+        }
+        self.superclass.as_ref()?.find_method(name)
+    }
+
+    /// 按名字查找静态成员，本类没有时沿继承链向上查找。
+    pub fn get_static(&self, name: &str) -> Option<MskValue> {
+        if let Some(value) = self.statics.borrow().get(name) {
+            return Some(value.clone());
+        }
+        self.superclass.as_ref()?.get_static(name)
+    }
+
+    /// 给静态字段赋值，总是写入本类，不会修改超类的同名成员。
+    pub fn set_static(&self, name: &str, value: MskValue) {
+        self.statics.borrow_mut().insert(name.to_string(), value);
+    }
+}
+
+/// 类的实例，字段可以在任何时候添加。
+pub struct MskInstance {
+    pub class: Rc<MskClass>,
+    pub fields: HashMap<String, MskValue>,
+}
+
+impl MskInstance {
+    /// 读取属性：字段优先于同名方法，方法会绑定到这个实例上。找不到时返回 None。
+    pub fn get(instance: &Rc<RefCell<MskInstance>>, name: &str) -> Option<MskValue> {
+        let this = instance.borrow();
+        if let Some(value) = this.fields.get(name) {
+            return Some(value.clone());
+        }
+        let method = this.class.find_method(name)?;
+        Some(MskValue::Callable(Rc::new(method.bind(MskValue::Instance(instance.clone())))))
+    }
+
+    pub fn set(&mut self, name: &str, value: MskValue) {
+        self.fields.insert(name.to_string(), value);
+    }
+}
+
+/// 把类包装成可调用对象：调用时创建新实例，如果类定义了 `init` 就以实参调用它。
+/// 参数个数的要求与 `init` 相同，没有 `init` 时不接受参数。
+pub struct Constructor {
+    pub class: Rc<MskClass>,
+}
+impl Callable for Constructor {
+    fn arity(&self) -> usize {
+        self.class.find_method("init").map_or(0, |init| init.arity())
+    }
+    fn max_arity(&self) -> Option<usize> {
+        self.class.find_method("init").map_or(Some(0), |init| init.max_arity())
+    }
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let instance = Rc::new(RefCell::new(MskInstance {
+            class: self.class.clone(),
+            fields: HashMap::new(),
+        }));
+        if let Some(init) = self.class.find_method("init") {
+            init.bind(MskValue::Instance(instance.clone())).call(interpreter, args)?;
+        }
+        Ok(MskValue::Instance(instance))
+    }
+}
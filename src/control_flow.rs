@@ -5,4 +5,5 @@ pub enum ControlFlow {
     Break,
     Continue,
     Return(MskValue), // Return 可以携带一个可选的返回值
+    LabeledBreak(String, MskValue), // `break label value;`，跳出同名的带标签块并携带一个值
 }
\ No newline at end of file
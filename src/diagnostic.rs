@@ -0,0 +1,63 @@
+//! diagnostic.rs - 统一描述扫描、解析和运行阶段产生的错误。
+//! 嵌入解释器的代码可以据此一次性拿到所有问题，而不必解析 stderr 输出。
+
+use std::fmt::{self, Display};
+use crate::interpreter::RuntimeError;
+
+/// 产生诊断信息的阶段。
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stage {
+    /// 读取源文件失败
+    Io,
+    /// 词法分析错误，例如未知字符、未结束的字符串
+    Scan,
+    /// 语法分析错误
+    Parse,
+    /// 静态解析错误，例如在变量自己的初始化表达式中读取它
+    Resolve,
+    /// 运行时错误
+    Runtime,
+}
+
+/// 一条诊断信息。
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub stage: Stage,
+    /// 出错的行号；部分运行时错误没有行号信息
+    pub line: Option<usize>,
+    /// 出错位置在行内的列号，从 1 开始。扫描、解析和静态解析错误带有列号，运行时错误没有
+    pub column: Option<usize>,
+    /// 完整的错误文本，与命令行模式下输出到 stderr 的内容一致
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(stage: Stage, line: Option<usize>, message: String) -> Self {
+        Diagnostic { stage, line, column: None, message }
+    }
+
+    /// 附上出错位置的列号。
+    pub fn with_column(mut self, column: usize) -> Self {
+        self.column = Some(column);
+        self
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<RuntimeError> for Diagnostic {
+    fn from(error: RuntimeError) -> Self {
+        let line = match &error {
+            RuntimeError::TypeError { line, .. }
+            | RuntimeError::NameError { line, .. }
+            | RuntimeError::IndexError { line, .. }
+            | RuntimeError::ArityError { line, .. } => Some(*line),
+            RuntimeError::Error(_) | RuntimeError::Control(_) => None,
+        };
+        Diagnostic::new(Stage::Runtime, line, error.to_string())
+    }
+}
@@ -15,6 +15,12 @@ pub struct Environment {
     parent: Option<Rc<RefCell<Environment>>>,
 }
 
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Environment {
     /// 创建一个新的空环境
     pub fn new() -> Self {
@@ -38,6 +44,11 @@ impl Environment {
         self.values.insert(name.into(), value);
     }
 
+    /// 本环境（不包括父环境）中定义的全部变量名
+    pub fn names(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+
     /// 获取变量的值
     /// 如果变量不存在，返回错误
     pub fn get(&self, name: &str,line: usize) -> Result<MskValue, RuntimeError> {
@@ -47,7 +58,7 @@ impl Environment {
                 match self.get_from_parent(name) {
                     Some(value) => Ok(value),
                     None => {
-                        Err(format!("[line {}] Undefined variable '{}'.", line, name).into())
+                        Err(RuntimeError::NameError { line, name: name.to_string() })
                     }
                 }
             }
@@ -64,19 +75,32 @@ impl Environment {
             None
         }
     }
-    pub fn assign(&mut self, name: &str, value: MskValue) -> Result<(), String> {
+    /// 给已经存在的变量赋值，沿父环境链查找
+    /// 如果变量不存在，返回带有行号 `line` 的错误
+    pub fn assign(&mut self, name: &str, value: MskValue, line: usize) -> Result<(), RuntimeError> {
         if self.values.contains_key(name) {
             self.values.insert(name.to_string(), value);
             Ok(())
         } else {
             match &self.parent{
-                None => Err(format!("Undefined variable '{}'.", name)),
+                None => Err(RuntimeError::NameError { line, name: name.to_string() }),
                 Some(p) => {
-                    p.borrow_mut().assign(name, value)
+                    p.borrow_mut().assign(name, value, line)
                 }
             }
         }
     }
+    /// 沿父环境链向上走 `distance` 层，返回对应的环境。
+    /// 距离由 resolver 计算，必然在链的长度之内。
+    pub fn ancestor(env: &Rc<RefCell<Environment>>, distance: usize) -> Rc<RefCell<Environment>> {
+        let mut current = env.clone();
+        for _ in 0..distance {
+            let parent = current.borrow().get_parent_env().expect("resolved depth exceeds scope chain");
+            current = parent;
+        }
+        current
+    }
+
     pub fn get_parent_env(&self) -> Option<Rc<RefCell<Environment>>> {
        match &self.parent{
            None => None,
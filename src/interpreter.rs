@@ -1,23 +1,88 @@
-use crate::ast::{Expr, Stmt};
+use crate::ast::{Expr, MatchArm, Pattern, Stmt};
 use crate::control_flow::ControlFlow;
 use crate::environment::Environment;
 use crate::msk_value::MskValue;
 use crate::token::{Literal, Token, TokenType};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::fmt::{Display, Formatter};
 use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::Path;
 use std::rc::Rc;
 use std::slice;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use log::info;
 use pretty_env_logger::env_logger::init_from_env;
 use crate::callable::Callable;
-use crate::native_fun::ClockNative;
+use crate::class::{Constructor, MskClass, MskInstance};
+use crate::diagnostic::{Diagnostic, Stage};
+use crate::native_fun::{
+    AbsNative, ArgvNative, ArityNative, AssertApproxNative, AssertNative, BenchNative, CeilNative,
+    ChrNative, ClockNative, ComposeNative, CurryNative, DropNative, EnumerateNative, EnvNative,
+    EprintNative, FlatMapNative, FlattenNative, FloorModNative, FloorNative, GetNative,
+    GroupDigitsNative, IdivNative, IsFiniteNative, IsNanNative, LenNative, MaxNative, MemoizeNative,
+    MinNative, NowIsoNative, NumNative, OrdNative, PartitionNative, PipeNative, PowNative,
+    PrettyPrintNative, RangeNative, ReadLineNative, ReprNative, ReverseMutNative, ReverseNative,
+    RoundNative, SbAppendNative, SbBuildNative, SbNewNative, SleepNative, SliceNative, SqrtNative,
+    StrNative, TakeNative, ToArrayNative, ToBinNative, ToHexNative, TypeNative,
+};
+use crate::native_fun::{max_value, min_value};
+use crate::parser::Parser;
 use crate::register_natives;
+use crate::resolver::Resolver;
+use crate::scanner::Scanner;
 use crate::user_fun::UserFunction;
 #[derive(Debug)]
 pub enum RuntimeError {
+    /// 没有更具体分类的运行时错误，消息中已经包含行号等信息
     Error(String),
+    /// 操作数或被调用者的类型不符合要求
+    TypeError { line: usize, message: String },
+    /// 访问了未定义的变量
+    NameError { line: usize, name: String },
+    /// 下标越界
+    IndexError { line: usize, index: i64 },
+    /// 调用时实参个数与形参个数不一致
+    ArityError { line: usize, name: String, min: usize, max: Option<usize>, got: usize },
+    /// 用于 break / continue / return 的控制流，不是真正的错误
     Control(ControlFlow),
 }
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeError::Error(message) => write!(f, "{}", message),
+            RuntimeError::TypeError { line, message } => write!(f, "[line {}] {}", line, message),
+            RuntimeError::NameError { line, name } => write!(f, "[line {}] Undefined variable '{}'.", line, name),
+            RuntimeError::IndexError { line, index } => write!(f, "[line {}] Index {} out of bounds.", line, index),
+            RuntimeError::ArityError { line, name, min, max, got } => match max {
+                Some(max) if max == min => {
+                    write!(f, "[line {}] Expected {} arguments but got {}.", line, min, got)
+                }
+                Some(max) => {
+                    write!(f, "[line {}] Function '{}' expected between {} and {} arguments but got {}.", line, name, min, max, got)
+                }
+                None => {
+                    write!(f, "[line {}] Function '{}' expected at least {} arguments but got {}.", line, name, min, got)
+                }
+            },
+            RuntimeError::Control(flow) => write!(f, "Unhandled control flow: {:?}", flow),
+        }
+    }
+}
+impl std::error::Error for RuntimeError {}
+impl RuntimeError {
+    /// 把离开了函数或整个程序的 break / continue / 带标签的 break 转换为普通错误，
+    /// 这样它不会被调用者所在的循环或带标签块拦截。`return` 和其他错误原样返回。
+    pub fn into_stray_error(self) -> RuntimeError {
+        match self {
+            RuntimeError::Control(ControlFlow::Break) => "Can't use 'break' outside of a loop.".to_string().into(),
+            RuntimeError::Control(ControlFlow::Continue) => "Can't use 'continue' outside of a loop.".to_string().into(),
+            RuntimeError::Control(ControlFlow::LabeledBreak(label, _)) => format!("No enclosing block labeled '{}'.", label).into(),
+            other => other,
+        }
+    }
+}
 impl From<String> for RuntimeError {
     fn from(error: String) -> Self {
         RuntimeError::Error(error)
@@ -25,34 +90,90 @@ impl From<String> for RuntimeError {
 }
 pub struct ScopeGuard<'a> {
     pub interpreter: &'a mut Interpreter,
+    /// 进入作用域之前的环境，退出时恢复
+    previous: Rc<RefCell<Environment>>,
 }
 
 impl<'a> ScopeGuard<'a> {
     pub fn new(interpreter: &'a mut Interpreter) -> Self {
-        interpreter.begin_scope();  // 构造时进入作用域
-        ScopeGuard { interpreter }
+        let parent = interpreter.env.clone();
+        ScopeGuard::with_parent(interpreter, parent)
+    }
+
+    /// 进入一个以 `parent` 为父环境的新作用域，用于函数调用：函数体的父环境是定义时捕获的闭包环境，
+    /// 而不是调用处的环境。
+    pub fn with_parent(interpreter: &'a mut Interpreter, parent: Rc<RefCell<Environment>>) -> Self {
+        let previous = interpreter.env.clone();
+        interpreter.env = Environment::new_with_parent(parent);  // 构造时进入作用域
+        ScopeGuard { interpreter, previous }
     }
 }
 
 impl<'a> Drop for ScopeGuard<'a> {
     fn drop(&mut self) {
-        self.interpreter.end_scope();  // 析构时自动退出作用域
+        self.interpreter.env = self.previous.clone();  // 析构时自动恢复进入前的环境
     }
 }
+/// 库默认允许的最大调用层数，刻意低于 CLI 使用的 1000 层。
+/// 解释器以递归方式求值，调试构建下每层 MskLang 调用大约占用 17KB 原生栈，1000 层需要约 20MB 栈；
+/// 200 层保证在 8MB 的主线程栈上先报告错误而不是栈溢出崩溃。
+/// 需要更深的递归时，在栈足够大的线程上运行解释器并调用 `set_max_call_depth`。
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 200;
+
 pub struct Interpreter {
     pub env: Rc<RefCell<Environment>>,
+    /// 全局环境。resolver 没有标注深度的变量都在这里查找
+    globals: Rc<RefCell<Environment>>,
+    /// 时间来源，返回自 Unix 纪元以来的时长。默认读取系统时间，测试时可替换。
+    clock: Box<dyn Fn() -> Duration>,
+    /// 暂停执行的方式，默认让当前线程休眠，测试时可替换为只记录时长而不真正等待。
+    sleeper: Box<dyn Fn(Duration)>,
+    /// `print` 语句的输出目标，默认是标准输出。
+    out: Box<dyn Write>,
+    /// 每次 `print` 之后是否立即刷新输出
+    autoflush: bool,
+    /// `eprint()` 的输出目标，默认是标准错误输出。
+    err: Box<dyn Write>,
+    /// 浮点数 `==` / `!=` 使用的相对误差，None 表示精确比较
+    float_tolerance: Option<f64>,
+    /// 单个循环允许执行的最大迭代次数，None 表示不限制
+    max_iterations: Option<usize>,
+    /// 当前正在执行的用户函数调用层数
+    call_depth: usize,
+    /// 允许的最大调用层数，超过后报告错误而不是让原生栈溢出
+    max_call_depth: usize,
+    /// 正在执行的调用表达式所在的行，原生函数回调传入的函数时用它报告错误
+    call_line: usize,
+    /// 替代进程环境变量的映射，设置后 `env()` 只从这里读取，便于测试
+    env_vars: Option<HashMap<String, String>>,
+    /// 传给脚本的命令行参数，由 `argv()` 返回
+    args: Vec<String>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
-    pub fn interpret(&mut self, stmt: &[Stmt]) -> Result<MskValue, RuntimeError> {
+    /// 静态解析并执行一组顶层语句。静态解析错误合并成一个 `RuntimeError::Error`，每行一条错误信息。
+    pub fn interpret(&mut self, stmts: &[Stmt]) -> Result<MskValue, RuntimeError> {
+        self.resolve(stmts)?;
+        let result = self.execute(stmts);
+        self.flush_on_error(result)
+    }
+
+    /// 执行已经过静态解析的语句，变量按 resolver 记录的距离查找。
+    pub(crate) fn execute(&mut self, stmt: &[Stmt]) -> Result<MskValue, RuntimeError> {
         for stmt in stmt {
             match stmt {
                 Stmt::Expression { expression } => {
-                    return self.evaluate(&expression)
+                    self.evaluate(expression)?;
                 }
                 Stmt::Print { expression } => {
                     let value = self.evaluate(&expression)?;
-                    println!("{}", value);
+                    self.write_output_line(&value.to_string())?;
                 }
                 Stmt::Var { name, initializer } => {
                     let value = if let Some(init) = initializer {
@@ -64,324 +185,1133 @@ impl Interpreter {
                 }
                 Stmt::Block { statements } => {
                     let guard = ScopeGuard::new(self);
-                    guard.interpreter.interpret(statements)?;
+                    guard.interpreter.execute(statements)?;
                 }
-                Stmt::If { name, condition, then_branch, else_branch } => {
-                    let condition = self.evaluate(&condition)?;
-                    // if let MskValue::Boolean(value) = condition {
-                    let value = condition.is_true();
-                    if value {
-                        let stmt_wrapper = slice::from_ref(&**then_branch);
-                        return self.interpret(stmt_wrapper)
-                    } else {
-                        if let Some(else_branch) = else_branch {
-                            let stmt_wrapper = slice::from_ref(&**else_branch);
-                            return self.interpret(stmt_wrapper)
-                            // let result = self.interpret(stmt_wrapper)?;
-                            // return Ok(result);
-                        }
-                    }
-                    // }else{
-                    //     return Err(format!("[line {}] Condition must be a boolean.", name.line));
-                    // }
+                Stmt::If { name, condition, then_branch, else_branch } => self.execute_if(condition, then_branch, else_branch.as_deref())?,
+                Stmt::While { name, binding, condition, body } => self.execute_while(name, binding.as_ref(), condition, body)?,
+                Stmt::ForEach { name, index_name, var_name, iterable, body } => self.execute_for_each(name, index_name.as_ref(), var_name, iterable, body)?,
+                Stmt::For { name, initializer, condition, increment, body } => self.execute_for(name, initializer.as_deref(), condition.as_ref(), increment.as_deref(), body)?,
+                Stmt::Break { label: None, .. } => {
+                    return Err(RuntimeError::Control(ControlFlow::Break));
                 }
-                Stmt::While { name, condition, body } => {
-                    let stmt_wrapper = slice::from_ref(&**body);
-                    while self.evaluate(condition)?.is_true() {
-                        match self.interpret(stmt_wrapper) {
-                            Ok(_) => {}, // 正常执行
-                            Err(RuntimeError::Control(ControlFlow::Break)) => {
-                                break; // 遇到 Break 语句，退出循环
-                            }
-                            Err(RuntimeError::Control(ControlFlow::Continue)) => {
-                                continue; // 遇到 Continue 语句，跳过当前循环迭代
+                Stmt::Break { label: Some(label), value, .. } => {
+                    let value = match value {
+                        Some(value) => self.evaluate(value)?,
+                        None => MskValue::Nil,
+                    };
+                    return Err(RuntimeError::Control(ControlFlow::LabeledBreak(label.lexeme.clone(), value)));
+                }
+                Stmt::Continue { .. } => {
+                    return Err(RuntimeError::Control(ControlFlow::Continue));
+                }
+                Stmt::Function { name, params, rest, body } => self.execute_function(name, params, rest.as_ref(), body),
+                Stmt::Class { name, superclass, methods, statics } => self.execute_class(name, superclass.as_ref(), methods, statics)?,
+                Stmt::Using { var_name, initializer, body, .. } => self.execute_using(var_name, initializer, body)?,
+                Stmt::Match { subject, arms, .. } => self.execute_match(subject, arms)?,
+                Stmt::Return { value, .. } => {
+                    // 以控制流的形式一路展开嵌套的块、循环和 if，直到被 UserFunction::call 捕获
+                    let value = match value {
+                        None => MskValue::Nil,
+                        Some(value) => self.evaluate(value)?,
+                    };
+                    return Err(RuntimeError::Control(ControlFlow::Return(value)));
+                }
+            }
+        }
+        Ok(MskValue::Nil)
+    }
+
+    /// 执行 if 语句，只执行条件选中的分支。
+    fn execute_if(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) -> Result<(), RuntimeError> {
+        let condition = self.evaluate(condition)?;
+        // if let MskValue::Boolean(value) = condition {
+        let value = condition.is_true();
+        // 分支执行完后继续执行 if 之后的语句；return 通过 ControlFlow::Return 向外传播
+        if value {
+            let stmt_wrapper = slice::from_ref(then_branch);
+            self.execute(stmt_wrapper)?;
+        } else if let Some(else_branch) = else_branch {
+            let stmt_wrapper = slice::from_ref(else_branch);
+            self.execute(stmt_wrapper)?;
+        }
+        // }else{
+        //     return Err(format!("[line {}] Condition must be a boolean.", name.line));
+        // }
+        Ok(())
+    }
+
+    /// 执行函数声明：创建捕获当前环境的函数并定义为变量。
+    fn execute_function(&mut self, name: &Token, params: &[Token], rest: Option<&Token>, body: &Rc<Stmt>) {
+        let func = MskValue::Callable(Rc::new(
+            UserFunction {
+                name: name.lexeme.clone(),
+                params: params.to_vec(),
+                rest: rest.cloned(),
+                body: body.clone(),
+                closure: self.env.clone(),
+            }
+        ));
+        self.env.borrow_mut().define(&name.lexeme, func);
+    }
+
+    /// 执行 while 循环，`binding` 是 `while (var x = ...)` 中绑定条件值的变量。
+    fn execute_while(&mut self, name: &Token, binding: Option<&Token>, condition: &Expr, body: &Stmt) -> Result<(), RuntimeError> {
+        let stmt_wrapper = slice::from_ref(body);
+        let mut iterations = 0;
+        loop {
+            let value = self.evaluate(condition)?;
+            if !value.is_true() {
+                break;
+            }
+            self.count_iteration(&mut iterations, name.line)?;
+            // 每次迭代都在新的作用域中执行循环体，循环体内声明的变量不会残留到下一次迭代，
+            // 即使循环体不是块语句也是如此
+            let guard = ScopeGuard::new(self);
+            if let Some(binding) = binding {
+                guard.interpreter.env.borrow_mut().define(&binding.lexeme, value);
+            }
+            match guard.interpreter.execute(stmt_wrapper) {
+                Ok(_) => {}, // 正常执行
+                Err(RuntimeError::Control(ControlFlow::Break)) => {
+                    break; // 遇到 Break 语句，退出循环
+                }
+                Err(RuntimeError::Control(ControlFlow::Continue)) => {
+                    continue; // 遇到 Continue 语句，跳过当前循环迭代
+                }
+                Err(e) => return Err(e), // 其他错误直接返回
+            }
+        }
+        Ok(())
+    }
+
+    /// 执行 `for (var x in iterable)` 循环，每次迭代在新的作用域中绑定当前元素。
+    fn execute_for_each(&mut self, name: &Token, index_name: Option<&Token>, var_name: &Token, iterable: &Expr, body: &Stmt) -> Result<(), RuntimeError> {
+        // 先取出全部元素，循环体修改列表不会影响本次遍历
+        let items: Vec<MskValue> = match self.evaluate(iterable)? {
+            MskValue::List(items) => items.borrow().clone(),
+            MskValue::String(s) => s.chars().map(|c| MskValue::String(c.to_string())).collect(),
+            other => {
+                return Err(RuntimeError::TypeError {
+                    line: name.line,
+                    message: format!("for-in expected a list or string but got {} ({}).", other, other.type_name()),
+                });
+            }
+        };
+        let stmt_wrapper = slice::from_ref(body);
+        let mut iterations = 0;
+        for (index, item) in items.into_iter().enumerate() {
+            self.count_iteration(&mut iterations, name.line)?;
+            let guard = ScopeGuard::new(self);
+            if let Some(index_name) = index_name {
+                guard.interpreter.env.borrow_mut().define(&index_name.lexeme, MskValue::Int(index as i64));
+            }
+            guard.interpreter.env.borrow_mut().define(&var_name.lexeme, item);
+            match guard.interpreter.execute(stmt_wrapper) {
+                Ok(_) | Err(RuntimeError::Control(ControlFlow::Continue)) => {}
+                Err(RuntimeError::Control(ControlFlow::Break)) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// 执行 C 风格的 for 循环，初始化语句声明的变量只在循环内可见。
+    fn execute_for(&mut self, name: &Token, initializer: Option<&Stmt>, condition: Option<&Expr>, increment: Option<&Stmt>, body: &Stmt) -> Result<(), RuntimeError> {
+        let guard = ScopeGuard::new(self);
+        // let stmt_wrapper = if let Stmt::Block { statements } = &**body {
+        //     statements.as_slice()
+        // } else {
+        //     slice::from_ref(body)
+        // };
+        let stmt_wrapper = slice::from_ref(body);
+        match initializer {
+            None => {}
+            Some(expr) => {
+                let expr_slice = slice::from_ref(expr);
+                guard.interpreter.execute(expr_slice)?;
+            }
+        }
+        let mut iterations = 0;
+        match condition {
+            Some(cond) => {
+                while guard.interpreter.evaluate(cond)?.is_true() {
+                    guard.interpreter.count_iteration(&mut iterations, name.line)?;
+                    match guard.interpreter.execute(stmt_wrapper) {
+                        Ok(_) => {}, // 正常执行
+                        Err(RuntimeError::Control(ControlFlow::Break)) => {
+                            break; // 遇到 Break 语句，退出循环
+                        }
+                        Err(RuntimeError::Control(ControlFlow::Continue)) => {
+                            if let Some(increment) = increment {
+                                guard.interpreter.execute(slice::from_ref(increment))?;
                             }
-                            Err(e) => return Err(e), // 其他错误直接返回
+                            continue; // 遇到 Continue 语句，跳过当前循环迭代
                         }
+                        Err(e) => return Err(e), // 其他错误直接返回
+                    }
+                    if let Some(increment) = increment {
+                        guard.interpreter.execute(slice::from_ref(increment))?;
                     }
                 }
-                Stmt::For { name, initializer, condition, increment, body } => {
-                    let guard = ScopeGuard::new(self);
-                    // let stmt_wrapper = if let Stmt::Block { statements } = &**body {
-                    //     statements.as_slice()
-                    // } else {
-                    //     slice::from_ref(&**body)
-                    // };
-                    let stmt_wrapper = slice::from_ref(&**body);
-                    match initializer.as_ref() {
-                        None => {}
-                        Some(expr) => {
-                            let expr_slice = slice::from_ref(expr.as_ref());
-                            guard.interpreter.interpret(expr_slice)?;
+            }
+            None => {
+                loop {
+                    guard.interpreter.count_iteration(&mut iterations, name.line)?;
+                    match guard.interpreter.execute(stmt_wrapper) {
+                        Ok(_) => {}, // 正常执行
+                        Err(RuntimeError::Control(ControlFlow::Break)) => {
+                            break; // 遇到 Break 语句，退出循环
                         }
-                    }
-                    match condition {
-                        Some(cond) => {
-                            while guard.interpreter.evaluate(cond)?.is_true() {
-                                match guard.interpreter.interpret(stmt_wrapper) {
-                                    Ok(_) => {}, // 正常执行
-                                    Err(RuntimeError::Control(ControlFlow::Break)) => {
-                                        break; // 遇到 Break 语句，退出循环
-                                    }
-                                    Err(RuntimeError::Control(ControlFlow::Continue)) => {
-                                        if let Some(increment) = increment.as_ref() {
-                                            guard.interpreter.interpret(slice::from_ref(&**increment))?;
-                                        }
-                                        continue; // 遇到 Continue 语句，跳过当前循环迭代
-                                    }
-                                    Err(e) => return Err(e), // 其他错误直接返回
-                                }
-                                if let Some(increment) = increment.as_ref() {
-                                    guard.interpreter.interpret(slice::from_ref(&**increment))?;
-                                }
+                        Err(RuntimeError::Control(ControlFlow::Continue)) => {
+                            if let Some(increment) = increment {
+                                guard.interpreter.execute(slice::from_ref(increment))?;
                             }
+                            continue; // 遇到 Continue 语句，跳过当前循环迭代
                         }
-                        None => {
-                            loop {
-                                match guard.interpreter.interpret(stmt_wrapper) {
-                                    Ok(_) => {}, // 正常执行
-                                    Err(RuntimeError::Control(ControlFlow::Break)) => {
-                                        break; // 遇到 Break 语句，退出循环
-                                    }
-                                    Err(RuntimeError::Control(ControlFlow::Continue)) => {
-                                        if let Some(increment) = increment.as_ref() {
-                                            guard.interpreter.interpret(slice::from_ref(&**increment))?;
-                                        }
-                                        continue; // 遇到 Continue 语句，跳过当前循环迭代
-                                    }
-                                    Err(e) => return Err(e), // 其他错误直接返回
-                                }
-                                if let Some(increment) = increment.as_ref() {
-                                    guard.interpreter.interpret(slice::from_ref(&**increment))?;
-                                }
-                            }
-                        } // 如果没有条件，直接进入循环
+                        Err(e) => return Err(e), // 其他错误直接返回
+                    }
+                    if let Some(increment) = increment {
+                        guard.interpreter.execute(slice::from_ref(increment))?;
                     }
                 }
-                Stmt::Break { .. } => {
-                    return Err(RuntimeError::Control(ControlFlow::Break));
-                }
-                Stmt::Continue { .. } => {
-                    return Err(RuntimeError::Control(ControlFlow::Continue));
+            } // 如果没有条件，直接进入循环
+        }
+        Ok(())
+    }
+
+    /// 执行类声明：求出超类，创建方法表和类对象，再初始化静态成员。
+    fn execute_class(&mut self, name: &Token, superclass: Option<&Expr>, methods: &[Stmt], statics: &[Stmt]) -> Result<(), RuntimeError> {
+        let superclass = match superclass {
+            Some(expr) => match self.evaluate(expr)? {
+                MskValue::Class(class) => Some(class),
+                _ => {
+                    return Err(RuntimeError::TypeError { line: name.line, message: "Superclass must be a class.".to_string() });
                 }
-                Stmt::Function { name, params, body } => {
-                    let func = MskValue::Callable(Rc::new(
-                        UserFunction {
-                            name: name.lexeme.clone(),
-                            params: params.clone(),
-                            body: (*body).clone(),
-                            closure: self.env.clone(),
-                        }
-                    ));
-                    self.env.borrow_mut().define(&name.lexeme, func);
+            },
+            None => None,
+        };
+        // 有超类时，方法的闭包外面多一层定义了 `super` 的环境
+        let closure = match &superclass {
+            Some(class) => {
+                let env = Environment::new_with_parent(self.env.clone());
+                env.borrow_mut().define("super", MskValue::Class(class.clone()));
+                env
+            }
+            None => self.env.clone(),
+        };
+        let mut table = HashMap::new();
+        for method in methods {
+            if let Stmt::Function { name, params, rest, body } = method {
+                table.insert(name.lexeme.clone(), Rc::new(UserFunction {
+                    name: name.lexeme.clone(),
+                    params: params.clone(),
+                    rest: rest.clone(),
+                    body: body.clone(),
+                    closure: closure.clone(),
+                }));
+            }
+        }
+        let class = Rc::new(MskClass {
+            name: name.lexeme.clone(),
+            superclass,
+            methods: table,
+            statics: RefCell::new(HashMap::new()),
+        });
+        self.env.borrow_mut().define(&name.lexeme, MskValue::Class(class.clone()));
+        // 类名定义之后再创建静态成员，这样静态字段的初始化表达式也能引用这个类
+        for member in statics {
+            match member {
+                Stmt::Function { name, params, rest, body } => {
+                    class.set_static(&name.lexeme, MskValue::Callable(Rc::new(UserFunction {
+                        name: name.lexeme.clone(),
+                        params: params.clone(),
+                        rest: rest.clone(),
+                        body: body.clone(),
+                        closure: self.env.clone(),
+                    })));
                 }
-                Stmt::Return { name, value } => {
-                    // info!("Returning the value: {:?}", value);
-                    return match value {
-                        None => {
-                            Ok(MskValue::Nil)
-                        }
-                        Some(value) => {
-                            // Err(RuntimeError::Control(ControlFlow::Return(self.evaluate(value)?)))
-                            Ok(self.evaluate(value)?)
-                        }
-                    }
+                Stmt::Var { name, initializer } => {
+                    let value = match initializer {
+                        Some(initializer) => self.evaluate(initializer)?,
+                        None => MskValue::Nil,
+                    };
+                    class.set_static(&name.lexeme, value);
                 }
+                _ => {}
             }
         }
-        Ok(MskValue::Nil)
+        Ok(())
     }
-}
-impl Interpreter {
-    /// 创建一个新的 Interpreter 实例。
-    fn begin_scope(&mut self) {
-        self.env = Environment::new_with_parent(self.env.clone());
-    }
-    fn end_scope(&mut self) {
-        let env = if let Some(value) = self.env.borrow().get_parent_env() {
-            value
-        }else{
-            Rc::new(RefCell::new(Environment::new()))
-        };
-        self.env = env;
+
+    /// 执行 `using` 语句：无论 body 如何结束，都会调用资源的 `close()`。
+    fn execute_using(&mut self, var_name: &Token, initializer: &Expr, body: &Stmt) -> Result<(), RuntimeError> {
+        let guard = ScopeGuard::new(self);
+        let resource = guard.interpreter.evaluate(initializer)?;
+        // 在执行 body 之前取出 close，资源不支持关闭时不执行 body
+        let close = guard.interpreter.close_method(var_name, &resource)?;
+        guard.interpreter.env.borrow_mut().define(&var_name.lexeme, resource);
+        let result = guard.interpreter.execute(slice::from_ref(body));
+        let closed = close.call(guard.interpreter, Vec::new());
+        // body 的错误（以及 return/break 等控制流）优先于 close 的错误
+        result?;
+        closed?;
+        Ok(())
+    }
+
+    /// 执行 match 语句，只执行第一个匹配成功的分支。
+    fn execute_match(&mut self, subject: &Expr, arms: &[MatchArm]) -> Result<(), RuntimeError> {
+        let value = self.evaluate(subject)?;
+        // 自上而下尝试每个分支，只执行第一个匹配成功的分支
+        for arm in arms {
+            let mut bindings = Vec::new();
+            if !self.pattern_matches(&arm.pattern, &value, &mut bindings)? {
+                continue;
+            }
+            let guard = ScopeGuard::new(self);
+            for (name, value) in bindings {
+                guard.interpreter.env.borrow_mut().define(&name.lexeme, value);
+            }
+            // match 没有贯穿，因此不是 break/continue 的目标：
+            // 分支里的控制流原样向外传播，交给外层循环处理
+            guard.interpreter.execute(slice::from_ref(&arm.body))?;
+            break;
+        }
+        Ok(())
     }
 }
 impl Interpreter {
     /// 创建一个新的 Interpreter 实例。
+    /// 最大调用层数为 `DEFAULT_MAX_CALL_DEPTH`（200 层），深递归的程序需要用 `set_max_call_depth` 调高。
+    /// 标准输出不是终端（被重定向到管道或文件）时 `print` 的输出会被缓冲，见 `set_autoflush`。
     pub fn new() -> Self {
         let global_env = Rc::new(RefCell::new(Environment::new()));
 
         // 使用宏注册所有原生函数
         register_natives!(global_env,
             "clock" => ClockNative,
-            // 在这里添加其他原生函数，例如：
-            // "sqrt" => SqrtNative,
+            "bench" => BenchNative,
+            "now_iso" => NowIsoNative,
+            "min" => MinNative,
+            "max" => MaxNative,
+            "eprint" => EprintNative,
+            "pretty_print" => PrettyPrintNative,
+            "repr" => ReprNative,
+            "memoize" => MemoizeNative,
+            "ord" => OrdNative,
+            "chr" => ChrNative,
+            "sb_new" => SbNewNative,
+            "sb_append" => SbAppendNative,
+            "sb_build" => SbBuildNative,
+            "env" => EnvNative,
+            "argv" => ArgvNative,
+            "floor_mod" => FloorModNative,
+            "idiv" => IdivNative,
+            "is_nan" => IsNanNative,
+            "is_finite" => IsFiniteNative,
+            "sleep" => SleepNative,
+            "to_hex" => ToHexNative,
+            "to_bin" => ToBinNative,
+            "group_digits" => GroupDigitsNative,
+            "len" => LenNative,
+            "flatten" => FlattenNative,
+            "flat_map" => FlatMapNative,
+            "partition" => PartitionNative,
+            "to_array" => ToArrayNative,
+            "range" => RangeNative,
+            "take" => TakeNative,
+            "drop" => DropNative,
+            "slice" => SliceNative,
+            "reverse" => ReverseNative,
+            "reverse_mut" => ReverseMutNative,
+            "get" => GetNative,
+            "enumerate" => EnumerateNative,
+            "assert" => AssertNative,
+            "assert_approx" => AssertApproxNative,
+            "type" => TypeNative,
+            "arity" => ArityNative,
+            "num" => NumNative,
+            "str" => StrNative,
+            "compose" => ComposeNative,
+            "pipe" => PipeNative,
+            "curry" => CurryNative,
+            "sqrt" => SqrtNative,
+            "abs" => AbsNative,
+            "floor" => FloorNative,
+            "ceil" => CeilNative,
+            "round" => RoundNative,
+            "pow" => PowNative,
+            "read_line" => ReadLineNative,
         );
 
+        // 标准输出是终端时每次 print 都立即刷新；被重定向到管道或文件时使用缓冲，减少系统调用
+        let autoflush = io::stdout().is_terminal();
+        let out: Box<dyn Write> = if autoflush {
+            Box::new(io::stdout())
+        } else {
+            Box::new(BufWriter::new(io::stdout()))
+        };
+
         Interpreter {
-            env: global_env,
+            env: global_env.clone(),
+            globals: global_env,
+            clock: Box::new(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap()),
+            sleeper: Box::new(std::thread::sleep),
+            out,
+            autoflush,
+            err: Box::new(io::stderr()),
+            float_tolerance: None,
+            max_iterations: None,
+            call_depth: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            call_line: 0,
+            env_vars: None,
+            args: Vec::new(),
+        }
+    }
+
+    /// 创建一个把 `print` 输出写入 `out` 的解释器，例如传入 `Vec<u8>` 以便在测试中检查输出。
+    pub fn with_output(out: impl Write + 'static) -> Self {
+        let mut interpreter = Interpreter::new();
+        interpreter.out = Box::new(out);
+        interpreter
+    }
+
+    /// 把 `eprint()` 的输出写入 `err` 而不是标准错误输出，便于在测试中与 `print` 的输出分开检查。
+    pub fn set_error_output(&mut self, err: impl Write + 'static) {
+        self.err = Box::new(err);
+    }
+
+    /// `print` 的输出目标，供 REPL 等需要直接写入输出的地方使用。
+    pub(crate) fn output(&mut self) -> &mut dyn Write {
+        &mut *self.out
+    }
+
+    /// 向 `print` 的输出目标写入一行，开启 autoflush 时立即刷新。
+    pub(crate) fn write_output_line(&mut self, text: &str) -> Result<(), RuntimeError> {
+        writeln!(self.out, "{}", text)
+            .and_then(|_| if self.autoflush { self.out.flush() } else { Ok(()) })
+            .map_err(|e| RuntimeError::Error(format!("Failed to write output: {}", e)))
+    }
+
+    /// 向错误输出写入一行并立即刷新。先刷新 `print` 的缓冲输出，保证两路输出的先后顺序。
+    pub(crate) fn write_error_line(&mut self, text: &str) -> Result<(), RuntimeError> {
+        self.out.flush()
+            .and_then(|_| writeln!(self.err, "{}", text))
+            .and_then(|_| self.err.flush())
+            .map_err(|e| RuntimeError::Error(format!("Failed to write error output: {}", e)))
+    }
+
+    /// 设置每次 `print` 之后是否立即刷新输出。默认在标准输出是终端时开启。
+    /// 关闭时输出可能会被缓冲，直到出错、写错误输出、调用 `flush` 或解释器被销毁时才写出。
+    pub fn set_autoflush(&mut self, enabled: bool) {
+        self.autoflush = enabled;
+    }
+
+    /// 写出缓冲的 `print` 输出。宿主程序在 `process::exit` 等不会销毁解释器的操作之前应调用它。
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    /// 把宿主程序提供的函数注册为全局变量 `name`，与内置原生函数的注册方式相同，返回自身以便链式调用。
+    /// 必须在 `interpret` / `evaluate` 之前调用：执行过程中当前环境可能是某个局部作用域。
+    pub fn register(&mut self, name: &str, f: Rc<dyn Callable>) -> &mut Self {
+        self.env.borrow_mut().define(name, MskValue::Callable(f));
+        self
+    }
+
+    /// 让两个浮点数之间的 `==` / `!=` 容忍相对误差 `epsilon`：
+    /// 当 `|l - r| <= epsilon * max(|l|, |r|)` 时视为相等。默认关闭，按 IEEE 754 精确比较。
+    pub fn set_float_tolerance(&mut self, epsilon: f64) {
+        self.float_tolerance = Some(epsilon);
+    }
+
+    /// 按当前的浮点容差比较两个浮点数是否相等。
+    fn floats_equal(&self, l: f64, r: f64) -> bool {
+        match self.float_tolerance {
+            // 先做精确比较，这样无穷大与自身仍然相等；NaN 在两种模式下都不等于任何值
+            Some(epsilon) => l == r || (l - r).abs() <= epsilon * l.abs().max(r.abs()),
+            None => l == r,
+        }
+    }
+
+    /// 用给定的映射代替进程环境变量，`env()` 之后只会从这个映射中读取。
+    pub fn set_env_vars(&mut self, vars: HashMap<String, String>) {
+        self.env_vars = Some(vars);
+    }
+
+    /// 读取一个环境变量，优先使用通过 `set_env_vars` 注入的映射。
+    pub fn env_var(&self, name: &str) -> Option<String> {
+        match &self.env_vars {
+            Some(vars) => vars.get(name).cloned(),
+            None => std::env::var(name).ok(),
+        }
+    }
+
+    /// 设置传给脚本的命令行参数，脚本中通过 `argv()` 按顺序读取。
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    /// 传给脚本的命令行参数。
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// 限制任意单个 `while` / `for` 循环的迭代次数，超过后报告运行时错误。
+    /// 用于防止脚本中的死循环拖垮宿主程序。
+    pub fn set_max_iterations(&mut self, max: usize) {
+        self.max_iterations = Some(max);
+    }
+
+    /// 设置用户函数调用的最大嵌套层数，默认为 `DEFAULT_MAX_CALL_DEPTH`。
+    /// 调试构建下每层约需 17KB 原生栈，例如 1000 层需要约 20MB；栈不够大时会在报告错误之前耗尽原生栈。
+    pub fn set_max_call_depth(&mut self, max: usize) {
+        self.max_call_depth = max;
+    }
+
+    /// 进入一层用户函数调用，超过最大调用层数时返回错误。
+    /// 成功时调用方必须在函数返回后调用 `exit_call`。
+    pub(crate) fn enter_call(&mut self) -> Result<(), RuntimeError> {
+        if self.call_depth >= self.max_call_depth {
+            return Err(RuntimeError::Error("Stack overflow: maximum call depth exceeded.".to_string()));
+        }
+        self.call_depth += 1;
+        Ok(())
+    }
+
+    pub(crate) fn exit_call(&mut self) {
+        self.call_depth -= 1;
+    }
+
+    /// 记录一次循环迭代，超过上限时返回指向循环所在行的错误。
+    fn count_iteration(&self, iterations: &mut usize, line: usize) -> Result<(), RuntimeError> {
+        *iterations += 1;
+        match self.max_iterations {
+            Some(max) if *iterations > max => {
+                Err(format!("[line {}] Loop exceeded maximum iterations.", line).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 替换解释器使用的时钟，`clock`、`now_iso` 等原生函数都会从这里取时间。
+    pub fn set_clock(&mut self, clock: impl Fn() -> Duration + 'static) {
+        self.clock = Box::new(clock);
+    }
+
+    /// 替换解释器暂停执行的方式，`sleep` 原生函数会通过它等待。
+    pub fn set_sleeper(&mut self, sleeper: impl Fn(Duration) + 'static) {
+        self.sleeper = Box::new(sleeper);
+    }
+
+    /// 读取、扫描、解析并执行一个源文件，相当于命令行的 `run` 命令。
+    /// 成功时返回程序的最终值（规则与 `run_program` 相同）；否则返回收集到的全部错误
+    /// （扫描、解析错误会一起返回），其中扫描、解析和静态解析错误带有行号和列号。
+    pub fn run_file(&mut self, path: &Path) -> Result<MskValue, Vec<Diagnostic>> {
+        let source = fs::read_to_string(path).map_err(|e| {
+            vec![Diagnostic::new(Stage::Io, None, format!("Failed to read file {}: {}", path.display(), e))]
+        })?;
+
+        let (tokens, mut diagnostics) = Scanner::new(&source).scan_tokens_with_diagnostics();
+        let mut parser = Parser::new(tokens);
+        let stmts = match parser.parse_with_diagnostics() {
+            Ok(stmts) => stmts,
+            Err(parse_diagnostics) => {
+                diagnostics.extend(parse_diagnostics);
+                Vec::new()
+            }
+        };
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        self.resolver().resolve(&stmts)?;
+        self.run_resolved(&stmts).map_err(|e| vec![e.into()])
+    }
+
+    /// 静态解析并执行一个完整的程序。如果最后一条语句是表达式语句，返回它的值，否则返回 nil；
+    /// 顶层的 `return` 提前结束程序，并返回它携带的值。
+    pub fn run_program(&mut self, stmts: &[Stmt]) -> Result<MskValue, RuntimeError> {
+        self.resolve(stmts)?;
+        self.run_resolved(stmts)
+    }
+
+    fn run_resolved(&mut self, stmts: &[Stmt]) -> Result<MskValue, RuntimeError> {
+        let result = match stmts.split_last() {
+            Some((Stmt::Expression { expression }, rest)) => {
+                self.execute(rest).and_then(|_| self.evaluate(expression))
+            }
+            _ => self.execute(stmts).map(|_| MskValue::Nil),
+        };
+        let result = match result.map_err(RuntimeError::into_stray_error) {
+            Err(RuntimeError::Control(ControlFlow::Return(value))) => Ok(value),
+            other => other,
+        };
+        self.flush_on_error(result)
+    }
+
+    /// 当前已定义的全局变量名，包括原生函数和宿主注册的函数。
+    pub fn global_names(&self) -> Vec<String> {
+        self.globals.borrow().names()
+    }
+
+    /// 创建一个知道当前全局变量的 resolver，这样用已有全局变量重新声明同名变量不会被当作读取自身。
+    fn resolver(&self) -> Resolver {
+        let mut resolver = Resolver::new();
+        resolver.set_known_globals(self.global_names());
+        resolver
+    }
+
+    /// 对顶层语句做静态解析，把所有错误合并成一个 `RuntimeError::Error`。
+    /// 重复解析同一棵语法树只会写入相同的距离，因此已经解析过的语句也可以再次传入。
+    fn resolve(&self, stmts: &[Stmt]) -> Result<(), RuntimeError> {
+        self.resolver().resolve(stmts).map_err(join_diagnostics)
+    }
+
+    /// 出错时先刷新缓冲的 `print` 输出，调用方随后报告错误或退出进程时输出不会丢失，也不会排在错误信息之后。
+    fn flush_on_error<T>(&mut self, result: Result<T, RuntimeError>) -> Result<T, RuntimeError> {
+        if result.is_err() {
+            let _ = self.out.flush();
         }
+        result
+    }
+
+    /// 调用原生函数收到的回调函数，例如 `flat_map` 的 `f`。
+    /// 参数个数不符时报告原生函数调用所在的行。
+    pub(crate) fn call_back(&mut self, func: &Rc<dyn Callable>, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        check_arity(self.call_line, "<anonymous>", &**func, args.len())?;
+        func.call(self, args)
+    }
+
+    /// 正在执行的调用表达式所在的行。
+    pub(crate) fn call_line(&self) -> usize {
+        self.call_line
+    }
+
+    /// 读取当前时间（自 Unix 纪元以来的时长）。
+    pub fn now(&self) -> Duration {
+        (self.clock)()
     }
 
-    /// 解释并执行给定的 AST 表达式。
+    /// 暂停执行给定的时长。
+    pub fn sleep(&self, duration: Duration) {
+        (self.sleeper)(duration)
+    }
+
+    /// 静态解析并求值单个表达式。静态解析错误与 `interpret` 一样合并成一个 `RuntimeError::Error`。
+    pub fn eval_expr(&mut self, expr: &Expr) -> Result<MskValue, RuntimeError> {
+        self.resolver().resolve_expression(expr).map_err(join_diagnostics)?;
+        let result = self.evaluate(expr);
+        self.flush_on_error(result)
+    }
+
+    /// 解释并执行给定的 AST 表达式，表达式必须已经过静态解析。
     /// 返回一个 Result，包含执行结果或错误信息。
-    pub fn evaluate(&mut self, expr: &Expr) -> Result<MskValue, RuntimeError> {
+    pub(crate) fn evaluate(&mut self, expr: &Expr) -> Result<MskValue, RuntimeError> {
         match expr {
             Expr::Unary { operator, right } => {
                 let value = self.evaluate(&*right)?;
                 self.evaluate_unary(&*operator, value)
             }
-            Expr::Binary { left, operator, right } => {
-                let left_value = self.evaluate(&*left)?;
-                let right_value = self.evaluate(&*right)?;
-                self.evaluate_binary(&operator, left_value, right_value)
-            }
+            Expr::Binary { left, operator, right } => self.evaluate_binary_expr(left, operator, right),
             Expr::Grouping { expression } => self.evaluate(&*expression),
-            Expr::Literal { value } => {
-                match value.token_type {
-                    TokenType::String => Ok(MskValue::String(value.literal.as_ref().unwrap().to_string())),
-                    TokenType::Number => {
-                        match value.literal.as_ref().unwrap() {
-                            Literal::Number(n) => Ok(MskValue::Float(*n)),
-                            _ => Err(format!("Unexpected number type for token: {}", value.lexeme).into()),
-                        }
-                    }
-                    TokenType::True => Ok(MskValue::Boolean(true)),
-                    TokenType::False => Ok(MskValue::Boolean(false)),
-                    TokenType::Nil => Ok(MskValue::Nil),
-                    _ => {
-                        Err(format!("Unexpected token type: {:?}", value.token_type).into())
+            Expr::Literal { value } => self.evaluate_literal(value),
+            Expr::Variable { name, depth } => self.look_up_variable(name, depth.get()),
+            // 解析器只在调用实参中产生展开，由 `Call` 分支直接处理
+            Expr::Spread { ellipsis, .. } => Err(RuntimeError::TypeError {
+                line: ellipsis.line,
+                message: "Spread is only allowed in call arguments.".to_string(),
+            }),
+            Expr::This { keyword, depth } => self.look_up_variable(keyword, depth.get()),
+            Expr::Super { keyword, method, depth } => self.evaluate_super(keyword, method, depth.get()),
+            Expr::Assign { name, value, depth } => {
+                let result = self.evaluate(&*value)?;
+                self.assign_variable(name, depth.get(), result.clone())?;
+                Ok(result)
+            }
+            Expr::MinMaxAssign { target, operator, value } => self.evaluate_min_max_assign(target, operator, value),
+            Expr::Ternary { condition, then_branch, else_branch }
+            | Expr::IfExpr { condition, then_branch, else_branch } => {
+                if self.evaluate(condition)?.is_true() {
+                    self.evaluate(then_branch)
+                } else {
+                    self.evaluate(else_branch)
+                }
+            }
+            Expr::Logical { left, operator, right } => self.evaluate_logical(left, operator, right),
+            Expr::Call { callee, paren, arguments } => self.evaluate_call(callee, paren, arguments),
+            Expr::Function { params, rest, body, .. } => {
+                Ok(MskValue::Callable(Rc::new(UserFunction {
+                    name: "lambda".to_string(),
+                    params: params.clone(),
+                    rest: rest.clone(),
+                    body: body.clone(),
+                    closure: self.env.clone(),
+                })))
+            }
+            Expr::LabeledBlock { label, statements } => self.evaluate_labeled_block(label, statements),
+            Expr::Get { object, name } => self.evaluate_get(object, name),
+            Expr::Set { object, name, value } => self.evaluate_set(object, name, value),
+            Expr::ListLiteral { elements, .. } => {
+                let mut items = Vec::with_capacity(elements.len());
+                for element in elements {
+                    items.push(self.evaluate(element)?);
+                }
+                Ok(MskValue::List(Rc::new(RefCell::new(items))))
+            }
+            Expr::Index { object, bracket, index } => self.evaluate_index(object, bracket, index),
+            Expr::SetIndex { object, bracket, index, value } => self.evaluate_set_index(object, bracket, index, value),
+            Expr::Tuple { paren, .. } => {
+                Err(format!("[line {}] Parenthesized lists can only be used in parallel assignment.", paren.line).into())
+            }
+            Expr::MultiAssign { names, depths, equals, value } => self.evaluate_multi_assign(names, depths, equals, value),
+        }
+    }
+
+    /// 求值二元表达式，左操作数是实例时先尝试运算符重载。
+    fn evaluate_binary_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> Result<MskValue, RuntimeError> {
+        let left_value = self.evaluate(left)?;
+        let right_value = self.evaluate(right)?;
+        if let MskValue::Instance(instance) = &left_value {
+            if let Some(result) = self.overloaded_binary(operator, instance, right_value.clone())? {
+                return Ok(result);
+            }
+        }
+        self.evaluate_binary(operator, left_value, right_value)
+    }
+
+    /// 求值 `and` / `or`，左操作数已经能决定结果时不求值右操作数。
+    fn evaluate_logical(&mut self, left: &Expr, operator: &Token, right: &Expr) -> Result<MskValue, RuntimeError> {
+        let left_value = self.evaluate(left)?;
+        if operator.token_type == TokenType::Or {
+            if left_value.is_true() {
+                return Ok(left_value);
+            }
+        } else if operator.token_type == TokenType::And {
+            if !left_value.is_true() {
+                return Ok(left_value);
+            }
+        }
+        let right_value = self.evaluate(right)?;
+        Ok(right_value)
+    }
+
+    /// 执行带标签的块，`break label value` 的值作为整个块的值。
+    fn evaluate_labeled_block(&mut self, label: &Token, statements: &[Stmt]) -> Result<MskValue, RuntimeError> {
+        let guard = ScopeGuard::new(self);
+        match guard.interpreter.execute(statements) {
+            Ok(_) => Ok(MskValue::Nil),
+            // 只拦截指向本块标签的 break，其余控制流继续向外传播
+            Err(RuntimeError::Control(ControlFlow::LabeledBreak(target, value))) if target == label.lexeme => Ok(value),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 读取列表元素 `list[i]`。
+    fn evaluate_index(&mut self, object: &Expr, bracket: &Token, index: &Expr) -> Result<MskValue, RuntimeError> {
+        let object = self.evaluate(object)?;
+        let index = self.evaluate(index)?;
+        let items = list_operand(bracket, &object)?;
+        let items = items.borrow();
+        let i = list_index(bracket, &index, items.len())?;
+        Ok(items[i].clone())
+    }
+
+    /// 给列表元素赋值 `list[i] = v`。
+    fn evaluate_set_index(&mut self, object: &Expr, bracket: &Token, index: &Expr, value: &Expr) -> Result<MskValue, RuntimeError> {
+        let object = self.evaluate(object)?;
+        let index = self.evaluate(index)?;
+        let value = self.evaluate(value)?;
+        let items = list_operand(bracket, &object)?;
+        let mut items = items.borrow_mut();
+        let i = list_index(bracket, &index, items.len())?;
+        items[i] = value.clone();
+        Ok(value)
+    }
+
+    /// 把字面量 Token 转换为运行时的值。
+    fn evaluate_literal(&self, value: &Token) -> Result<MskValue, RuntimeError> {
+        match value.token_type {
+            TokenType::String => Ok(MskValue::String(value.literal.as_ref().unwrap().to_string())),
+            TokenType::Number => {
+                match value.literal.as_ref().unwrap() {
+                    Literal::Number(n) => Ok(MskValue::Float(*n)),
+                    Literal::Integer(n) => Ok(MskValue::Int(*n)),
+                    _ => Err(format!("Unexpected number type for token: {}", value.lexeme).into()),
+                }
+            }
+            TokenType::True => Ok(MskValue::Boolean(true)),
+            TokenType::False => Ok(MskValue::Boolean(false)),
+            TokenType::Nil => Ok(MskValue::Nil),
+            _ => {
+                Err(format!("Unexpected token type: {:?}", value.token_type).into())
+            }
+        }
+    }
+
+    /// 求值 `super.method`：在超类中查找方法并绑定到当前的 `this`。
+    fn evaluate_super(&mut self, keyword: &Token, method: &Token, depth: Option<usize>) -> Result<MskValue, RuntimeError> {
+        // `super` 所在的环境紧挨在定义 `this` 的环境外面
+        let distance = depth.unwrap_or(0);
+        let superclass = Environment::ancestor(&self.env, distance).borrow().get("super", keyword.line)?;
+        let this = Environment::ancestor(&self.env, distance.saturating_sub(1)).borrow().get("this", keyword.line)?;
+        let MskValue::Class(superclass) = superclass else {
+            return Err(RuntimeError::TypeError { line: keyword.line, message: "Superclass must be a class.".to_string() });
+        };
+        match superclass.find_method(&method.lexeme) {
+            Some(found) => Ok(MskValue::Callable(Rc::new(found.bind(this)))),
+            None => Err(RuntimeError::TypeError {
+                line: method.line,
+                message: format!("Undefined property '{}'.", method.lexeme),
+            }),
+        }
+    }
+
+    /// 求值函数调用：展开实参、检查参数个数，再调用函数或构造实例。
+    fn evaluate_call(&mut self, callee: &Expr, paren: &Token, arguments: &[Expr]) -> Result<MskValue, RuntimeError> {
+        let callee_value = self.evaluate(callee)?;
+        let mut args = Vec::new();
+        // info!("Callee: {:?}, Arguments: {:?}", callee_value, arguments);
+        for arg in arguments {
+            match arg {
+                Expr::Spread { ellipsis, expression } => match self.evaluate(expression)? {
+                    MskValue::List(items) => args.extend(items.borrow().iter().cloned()),
+                    other => {
+                        return Err(RuntimeError::TypeError {
+                            line: ellipsis.line,
+                            message: format!("Can only spread a list but got {} ({}).", other, other.type_name()),
+                        });
                     }
+                },
+                _ => args.push(self.evaluate(arg)?),
+            }
+        }
+        let func: Rc<dyn Callable> = match callee_value {
+            MskValue::Callable(func) => func,
+            // 调用类会创建一个新的实例
+            MskValue::Class(class) => Rc::new(Constructor { class }),
+            _ => {
+                return Err(RuntimeError::TypeError { line: paren.line, message: "Can only call functions and classes.".to_string() });
+            }
+        };
+        let name = match callee {
+            Expr::Variable { name, .. } => name.lexeme.as_str(),
+            _ => "<anonymous>",
+        };
+        check_arity(paren.line, name, &*func, args.len())?;
+        let enclosing_line = std::mem::replace(&mut self.call_line, paren.line);
+        let result = func.call(self, args);
+        self.call_line = enclosing_line;
+        result
+        // let result = func.call(self, args);
+        // info!("Result: {:?}",  result);
+        // result
+    }
+
+    /// 读取实例的属性或类的静态成员。
+    fn evaluate_get(&mut self, object: &Expr, name: &Token) -> Result<MskValue, RuntimeError> {
+        match self.evaluate(object)? {
+            MskValue::Instance(instance) => MskInstance::get(&instance, &name.lexeme).ok_or_else(|| {
+                RuntimeError::TypeError { line: name.line, message: format!("Undefined property '{}'.", name.lexeme) }
+            }),
+            MskValue::Class(class) => class.get_static(&name.lexeme).ok_or_else(|| {
+                RuntimeError::TypeError { line: name.line, message: format!("Undefined static member '{}'.", name.lexeme) }
+            }),
+            _ => Err(RuntimeError::TypeError { line: name.line, message: "Only instances and classes have properties.".to_string() }),
+        }
+    }
+
+    /// 给实例的字段或类的静态成员赋值。
+    fn evaluate_set(&mut self, object: &Expr, name: &Token, value: &Expr) -> Result<MskValue, RuntimeError> {
+        match self.evaluate(object)? {
+            MskValue::Instance(instance) => {
+                let value = self.evaluate(value)?;
+                instance.borrow_mut().set(&name.lexeme, value.clone());
+                Ok(value)
+            }
+            MskValue::Class(class) => {
+                let value = self.evaluate(value)?;
+                class.set_static(&name.lexeme, value.clone());
+                Ok(value)
+            }
+            _ => Err(RuntimeError::TypeError { line: name.line, message: "Only instances and classes have fields.".to_string() }),
+        }
+    }
+
+    /// 执行并行赋值 `(a, b) = ...`。
+    fn evaluate_multi_assign(&mut self, names: &[Token], depths: &[Cell<Option<usize>>], equals: &Token, value: &Expr) -> Result<MskValue, RuntimeError> {
+        // 先完整求出右侧的所有值，再逐个赋值，这样 `(a, b) = (b, a)` 才能正确交换
+        let values = match value {
+            Expr::Tuple { elements, .. } => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements {
+                    values.push(self.evaluate(element)?);
                 }
+                values
+            }
+            // 右侧也可以是求值为列表的表达式，例如返回多个值的函数调用 `(q, r) = divmod(7, 2)`
+            other => match self.evaluate(other)? {
+                MskValue::List(items) => items.borrow().clone(),
+                _ => return Err(format!("[line {}] Parallel assignment expects a parenthesized list of values or a list.", equals.line).into()),
             },
-            Expr::Variable { name } => {
-                self.env.borrow().get(&name.lexeme,name.line)
+        };
+        if values.len() != names.len() {
+            return Err(format!("[line {}] Expected {} values in parallel assignment but got {}.", equals.line, names.len(), values.len()).into());
+        }
+        for ((name, depth), value) in names.iter().zip(depths).zip(values) {
+            self.assign_variable(name, depth.get(), value)?;
+        }
+        Ok(MskValue::Nil)
+    }
+
+    /// 执行 `x min= v` / `x max= v`，目标可以是变量或列表元素。
+    fn evaluate_min_max_assign(&mut self, target: &Expr, operator: &Token, value: &Expr) -> Result<MskValue, RuntimeError> {
+        match target {
+            Expr::Variable { name, depth } => {
+                let current = self.look_up_variable(name, depth.get())?;
+                let value = self.evaluate(value)?;
+                let result = min_max(operator, &current, &value)?;
+                self.assign_variable(name, depth.get(), result.clone())?;
+                Ok(result)
             }
-            Expr::Assign { name, value } => {
-                let result = self.evaluate(&*value)?;
-                self.env.borrow_mut().assign(&name.lexeme,result.clone())?;
+            Expr::Index { object, bracket, index } => {
+                // 列表和下标只求值一次
+                let object = self.evaluate(object)?;
+                let index = self.evaluate(index)?;
+                let items = list_operand(bracket, &object)?;
+                let current = {
+                    let items = items.borrow();
+                    items[list_index(bracket, &index, items.len())?].clone()
+                };
+                let value = self.evaluate(value)?;
+                let result = min_max(operator, &current, &value)?;
+                // 右侧求值时列表可能被修改，写回前重新检查下标
+                let mut items = items.borrow_mut();
+                let i = list_index(bracket, &index, items.len())?;
+                items[i] = result.clone();
                 Ok(result)
             }
-            Expr::Logical { left, operator, right } => {
-                let left_value = self.evaluate(&*left)?;
-                if operator.token_type == TokenType::Or {
-                    if left_value.is_true() {
-                        return Ok(left_value);
-                    }
-                } else if operator.token_type == TokenType::And {
-                    if !left_value.is_true() {
-                        return Ok(left_value);
-                    }
-                }
-                let right_value = self.evaluate(&*right)?;
-                Ok(right_value)
-            }
-            Expr::Call { callee, paren, arguments } => {
-                let callee_value = self.evaluate(&*callee)?;
-                let mut args = Vec::new();
-                // info!("Callee: {:?}, Arguments: {:?}", callee_value, arguments);
-                for arg in arguments {
-                    args.push(self.evaluate(&*arg)?);
+            _ => Err(format!("[line {}] Invalid assignment target.", operator.line).into()),
+        }
+    }
+    /// 取出 `using` 资源的 `close` 属性，它必须是不需要参数的可调用对象。
+    fn close_method(&self, var_name: &Token, resource: &MskValue) -> Result<Rc<dyn Callable>, RuntimeError> {
+        let close = match resource {
+            MskValue::Instance(instance) => MskInstance::get(instance, "close"),
+            _ => None,
+        };
+        match close {
+            Some(MskValue::Callable(close)) if close.min_arity() == 0 => Ok(close),
+            _ => Err(RuntimeError::TypeError {
+                line: var_name.line,
+                message: format!("Resource '{}' must have a close() method but got {} ({}).", var_name.lexeme, resource, resource.type_name()),
+            }),
+        }
+    }
+    /// 判断值是否匹配给定的模式，匹配成功时把模式中的绑定依次追加到 `bindings`。
+    fn pattern_matches<'p>(&mut self, pattern: &'p Pattern, value: &MskValue, bindings: &mut Vec<(&'p Token, MskValue)>) -> Result<bool, RuntimeError> {
+        match pattern {
+            Pattern::Wildcard => Ok(true),
+            Pattern::Binding(name) => {
+                bindings.push((name, value.clone()));
+                Ok(true)
+            }
+            Pattern::List(patterns) => {
+                let MskValue::List(items) = value else {
+                    return Ok(false);
+                };
+                // 先复制元素，子模式求值时不持有列表的借用
+                let items = items.borrow().clone();
+                if items.len() != patterns.len() {
+                    return Ok(false);
                 }
-                if let MskValue::Callable(func) = callee_value {
-                    if args.len() != func.arity() {
-                        return Err(format!("[line {}] Expected {} arguments but got {}.", paren.line, func.arity(), args.len()).into());
+                for (pattern, item) in patterns.iter().zip(&items) {
+                    if !self.pattern_matches(pattern, item, bindings)? {
+                        return Ok(false);
                     }
-                    func.call(self, args)
-                    // let result = func.call(self, args);
-                    // info!("Result: {:?}",  result);
-                    // result
-                } else {
-                    Err(format!("[line {}] Can only call functions and classes.", paren.line).into())
                 }
+                Ok(true)
+            }
+            Pattern::Literal(token) => {
+                let expected = self.evaluate(&Expr::Literal { value: token.clone() })?;
+                Ok(match (&expected, value) {
+                    (MskValue::Int(l), MskValue::Int(r)) => l == r,
+                    (MskValue::Float(l), MskValue::Float(r)) => l == r,
+                    (MskValue::Int(l), MskValue::Float(r)) => *l as f64 == *r,
+                    (MskValue::Float(l), MskValue::Int(r)) => *l == *r as f64,
+                    (MskValue::String(l), MskValue::String(r)) => l == r,
+                    (MskValue::Boolean(l), MskValue::Boolean(r)) => l == r,
+                    (MskValue::Nil, MskValue::Nil) => true,
+                    _ => false,
+                })
             }
         }
     }
+    /// 左操作数是实例且它的类定义了对应的方法时，以右操作数为参数调用该方法：
+    /// `+` 对应 `add`，`==` / `!=` 对应 `equals`，比较运算符对应 `compare`（返回负数、零或正数）。
+    /// 没有对应的方法时返回 None，按普通的二元运算处理。
+    fn overloaded_binary(&mut self, operator: &Token, instance: &Rc<RefCell<MskInstance>>, right: MskValue) -> Result<Option<MskValue>, RuntimeError> {
+        let name = match operator.token_type {
+            TokenType::Plus => "add",
+            TokenType::EqualEqual | TokenType::BangEqual => "equals",
+            TokenType::Less | TokenType::LessEqual | TokenType::Greater | TokenType::GreaterEqual => "compare",
+            _ => return Ok(None),
+        };
+        let Some(method) = instance.borrow().class.find_method(name) else {
+            return Ok(None);
+        };
+        let method = method.bind(MskValue::Instance(instance.clone()));
+        check_arity(operator.line, name, &method, 1)?;
+        let result = method.call(self, vec![right])?;
+        let ordering = match operator.token_type {
+            TokenType::Plus => return Ok(Some(result)),
+            TokenType::EqualEqual => return Ok(Some(MskValue::Boolean(result.is_true()))),
+            TokenType::BangEqual => return Ok(Some(MskValue::Boolean(!result.is_true()))),
+            _ => result.as_f64().ok_or_else(|| RuntimeError::TypeError {
+                line: operator.line,
+                message: format!("compare() must return a number but got {} ({}).", result, result.type_name()),
+            })?,
+        };
+        let value = match operator.token_type {
+            TokenType::Less => ordering < 0.0,
+            TokenType::LessEqual => ordering <= 0.0,
+            TokenType::Greater => ordering > 0.0,
+            _ => ordering >= 0.0,
+        };
+        Ok(Some(MskValue::Boolean(value)))
+    }
+
+    /// 按 resolver 计算出的距离给变量赋值，没有距离的是全局变量。
+    fn assign_variable(&mut self, name: &Token, depth: Option<usize>, value: MskValue) -> Result<(), RuntimeError> {
+        match depth {
+            Some(distance) => Environment::ancestor(&self.env, distance).borrow_mut().assign(&name.lexeme, value, name.line),
+            None => self.globals.borrow_mut().assign(&name.lexeme, value, name.line),
+        }
+    }
+
+    /// 按 resolver 计算出的距离查找变量，没有距离的是全局变量。
+    fn look_up_variable(&self, name: &Token, depth: Option<usize>) -> Result<MskValue, RuntimeError> {
+        match depth {
+            Some(distance) => Environment::ancestor(&self.env, distance).borrow().get(&name.lexeme, name.line),
+            None => self.globals.borrow().get(&name.lexeme, name.line),
+        }
+    }
+
     fn evaluate_binary(&self, operator: &Token, left: MskValue, right: MskValue) -> Result<MskValue, RuntimeError> {
         match operator.token_type {
-            TokenType::Plus => match (left, right) {
-                (MskValue::Float(l), MskValue::Float(r)) => Ok(MskValue::Float(l + r)),
+            TokenType::Plus => match (&left, &right) {
                 (MskValue::String(l), MskValue::String(r)) => Ok(MskValue::String(format!("{}{}", l, r))),
-                _ => Err(format!("[line {}] Operands must be two numbers or two strings for '+' operator.", operator.line).into()),
+                _ => match numbers(&left, &right) {
+                    Some(Numbers::Ints(l, r)) => Ok(int_or_float(l.checked_add(r), l as f64 + r as f64)),
+                    Some(Numbers::Floats(l, r)) => Ok(MskValue::Float(l + r)),
+                    None => Err(operand_error(operator, "two numbers or two strings", &left, &right)),
+                },
             },
-            TokenType::Minus => match (left, right) {
-                (MskValue::Float(l), MskValue::Float(r)) => Ok(MskValue::Float(l - r)),
-                _ => Err(format!("[line {}] Operands must be numbers for '-' operator.", operator.line).into()),
+            TokenType::Minus => match numbers(&left, &right) {
+                Some(Numbers::Ints(l, r)) => Ok(int_or_float(l.checked_sub(r), l as f64 - r as f64)),
+                Some(Numbers::Floats(l, r)) => Ok(MskValue::Float(l - r)),
+                None => Err(operand_error(operator, "numbers", &left, &right)),
             },
-            TokenType::Star => {
-                if let (MskValue::Float(l), MskValue::Float(r)) = (left, right) {
-                    Ok(MskValue::Float(l * r))
-                } else {
-                    Err(format!("[line {}] Operands must be numbers for '*' operator.", operator.line).into())
-                }
+            TokenType::Star => match numbers(&left, &right) {
+                Some(Numbers::Ints(l, r)) => Ok(int_or_float(l.checked_mul(r), l as f64 * r as f64)),
+                Some(Numbers::Floats(l, r)) => Ok(MskValue::Float(l * r)),
+                None => Err(operand_error(operator, "numbers", &left, &right)),
             },
-            TokenType::Slash => {
-                if let (MskValue::Float(l), MskValue::Float(r)) = (left, right) {
-                    if r == 0.0 {
-                        Err(format!("[line {}] Division by zero is not allowed.", operator.line).into())
-                    } else {
-                        Ok(MskValue::Float(l / r))
-                    }
-                } else {
-                    Err(format!("[line {}] Operands must be numbers for '/' operator.", operator.line).into())
+            // `/` 总是得到浮点数（`7 / 2` 为 3.5），整数除法使用 `idiv(a, b)`
+            TokenType::Slash => match (left.as_f64(), right.as_f64()) {
+                (Some(_), Some(0.0)) => {
+                    Err(format!("[line {}] Division by zero is not allowed.", operator.line).into())
                 }
+                (Some(l), Some(r)) => Ok(MskValue::Float(l / r)),
+                _ => Err(operand_error(operator, "numbers", &left, &right)),
             },
-            TokenType::Greater => {
-                if let (MskValue::Float(l), MskValue::Float(r)) = (left, right) {
-                    Ok(MskValue::Boolean(l > r))
-                } else {
-                    Err(format!("[line {}] Operands must be numbers for '>' operator.", operator.line).into())
+            // 取余沿用 Rust 的 `%` 语义，结果符号与被除数相同
+            TokenType::Percent => match numbers(&left, &right) {
+                Some(Numbers::Ints(_, 0)) | Some(Numbers::Floats(_, 0.0)) => {
+                    Err(format!("[line {}] Modulo by zero is not allowed.", operator.line).into())
                 }
+                Some(Numbers::Ints(l, r)) => Ok(int_or_float(l.checked_rem(r), l as f64 % r as f64)),
+                Some(Numbers::Floats(l, r)) => Ok(MskValue::Float(l % r)),
+                None => Err(operand_error(operator, "numbers", &left, &right)),
             },
-            TokenType::GreaterEqual => {
-                if let (MskValue::Float(l), MskValue::Float(r)) = (left, right) {
-                    Ok(MskValue::Boolean(l >= r))
-                } else {
-                    Err(format!("[line {}] Operands must be numbers for '>=' operator.", operator.line).into())
-                }
+            TokenType::Greater => match (&left, &right) {
+                // 布尔值按 false < true 比较
+                (MskValue::Boolean(l), MskValue::Boolean(r)) => Ok(MskValue::Boolean(l > r)),
+                _ => match numbers(&left, &right) {
+                    Some(Numbers::Ints(l, r)) => Ok(MskValue::Boolean(l > r)),
+                    Some(Numbers::Floats(l, r)) => Ok(MskValue::Boolean(l > r)),
+                    None => Err(operand_error(operator, "two numbers or two booleans", &left, &right)),
+                },
             },
-            TokenType::Less => {
-                if let (MskValue::Float(l), MskValue::Float(r)) = (left, right) {
-                    Ok(MskValue::Boolean(l < r))
-                } else {
-                    Err(format!("[line {}] Operands must be numbers for '<' operator.", operator.line).into())
-                }
+            TokenType::GreaterEqual => match (&left, &right) {
+                (MskValue::Boolean(l), MskValue::Boolean(r)) => Ok(MskValue::Boolean(l >= r)),
+                _ => match numbers(&left, &right) {
+                    Some(Numbers::Ints(l, r)) => Ok(MskValue::Boolean(l >= r)),
+                    Some(Numbers::Floats(l, r)) => Ok(MskValue::Boolean(l >= r)),
+                    None => Err(operand_error(operator, "two numbers or two booleans", &left, &right)),
+                },
             },
-            TokenType::LessEqual => {
-                if let (MskValue::Float(l), MskValue::Float(r)) = (left, right) {
-                    Ok(MskValue::Boolean(l <= r))
-                } else {
-                    Err(format!("[line {}] Operands must be numbers for '<=' operator.", operator.line).into())
-                }
-            }
-            TokenType::EqualEqual => {
-                match (left,right) { 
-                    (MskValue::Float(l), MskValue::Float(r)) => Ok(MskValue::Boolean(l == r)),
-                    (MskValue::String(l), MskValue::String(r)) => Ok(MskValue::Boolean(l == r)),
-                    (MskValue::Boolean(l), MskValue::Boolean(r)) => Ok(MskValue::Boolean(l == r)),
-                    // (MskValue::Nil, MskValue::Nil) => Ok(MskValue::Boolean(true)),
-                    _ => Ok(MskValue::Boolean(false)), // 不同类型的比较返回 false
-                }
-            }
-            TokenType::BangEqual => {
-                match (left,right) {
-                    (MskValue::Float(l), MskValue::Float(r)) => Ok(MskValue::Boolean(l != r)),
-                    (MskValue::String(l), MskValue::String(r)) => Ok(MskValue::Boolean(l != r)),
-                    (MskValue::Boolean(l), MskValue::Boolean(r)) => Ok(MskValue::Boolean(l != r)),
-                    // (MskValue::Nil, MskValue::Nil) => Ok(MskValue::Boolean(true)),
-                    _ => Ok(MskValue::Boolean(true)), 
-                }
+            TokenType::Less => match (&left, &right) {
+                (MskValue::Boolean(l), MskValue::Boolean(r)) => Ok(MskValue::Boolean(l < r)),
+                _ => match numbers(&left, &right) {
+                    Some(Numbers::Ints(l, r)) => Ok(MskValue::Boolean(l < r)),
+                    Some(Numbers::Floats(l, r)) => Ok(MskValue::Boolean(l < r)),
+                    None => Err(operand_error(operator, "two numbers or two booleans", &left, &right)),
+                },
+            },
+            TokenType::LessEqual => match (&left, &right) {
+                (MskValue::Boolean(l), MskValue::Boolean(r)) => Ok(MskValue::Boolean(l <= r)),
+                _ => match numbers(&left, &right) {
+                    Some(Numbers::Ints(l, r)) => Ok(MskValue::Boolean(l <= r)),
+                    Some(Numbers::Floats(l, r)) => Ok(MskValue::Boolean(l <= r)),
+                    None => Err(operand_error(operator, "two numbers or two booleans", &left, &right)),
+                },
             }
+            // 成员测试 `x in collection`：字符串判断左侧是否为右侧的子串，列表判断是否有与左侧 `==` 的元素
+            TokenType::In => match (&left, &right) {
+                (MskValue::String(needle), MskValue::String(haystack)) => Ok(MskValue::Boolean(haystack.contains(needle.as_str()))),
+                (_, MskValue::List(items)) => Ok(MskValue::Boolean(items.borrow().iter().any(|item| self.values_equal(&left, item)))),
+                _ => Err(operand_error(operator, "a string or a list on the right", &left, &right)),
+            },
+            TokenType::EqualEqual => Ok(MskValue::Boolean(self.values_equal(&left, &right))),
+            TokenType::BangEqual => Ok(MskValue::Boolean(!self.values_equal(&left, &right))),
             _ => Err(format!("[line {}] Unsupported binary operator: {:?}", operator.line, operator).into()),
         }
     }
+    /// `==` 的语义，`!=` 与 `in` 也使用它。不同类型的值总是不相等。
+    fn values_equal(&self, left: &MskValue, right: &MskValue) -> bool {
+        match (left, right) {
+            // 数字比较必须保持 IEEE 754 语义：`0.0 == -0.0` 为 true，NaN 与任何值（包括自身）都不相等。
+            // 因此这里直接使用 f64 的 `==`，不要改成按位比较。开启浮点容差时改为近似比较。
+            (MskValue::Float(l), MskValue::Float(r)) => self.floats_equal(*l, *r),
+            (MskValue::Int(l), MskValue::Int(r)) => l == r,
+            // 整数与浮点数比较时先提升为浮点数，因此 `1 == 1.0` 为 true
+            (MskValue::Int(l), MskValue::Float(r)) => *l as f64 == *r,
+            (MskValue::Float(l), MskValue::Int(r)) => *l == *r as f64,
+            (MskValue::String(l), MskValue::String(r)) => l == r,
+            (MskValue::Boolean(l), MskValue::Boolean(r)) => l == r,
+            (MskValue::Nil, MskValue::Nil) => true,
+            // 列表、实例、类等引用类型按引用比较，只有同一个对象才相等；
+            // 内容相同的两个列表并不相等，这也避免了比较自引用的列表时无限递归
+            (MskValue::List(l), MskValue::List(r)) => Rc::ptr_eq(l, r),
+            (MskValue::StringBuilder(l), MskValue::StringBuilder(r)) => Rc::ptr_eq(l, r),
+            (MskValue::Callable(l), MskValue::Callable(r)) => Rc::ptr_eq(l, r),
+            (MskValue::Instance(l), MskValue::Instance(r)) => Rc::ptr_eq(l, r),
+            (MskValue::Class(l), MskValue::Class(r)) => Rc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
     fn evaluate_unary(&self, operator: &Token, value: MskValue) -> Result<MskValue, RuntimeError> {
         match operator.token_type {
             TokenType::Minus => {
-                if let MskValue::Float(n) = value {
+                if let MskValue::Int(n) = value {
+                    Ok(int_or_float(n.checked_neg(), -(n as f64)))
+                } else if let MskValue::Float(n) = value {
                     Ok(MskValue::Float(-n))
                 } else {
-                    Err(format!("[line {}] Operand must be a number.", operator.line).into())
+                    Err(RuntimeError::TypeError {
+                        line: operator.line,
+                        message: format!("'{}' expected a number but got {}.", operator.lexeme, describe_operand(&value)),
+                    })
                 }
             }
             TokenType::Bang => {
@@ -390,4 +1320,103 @@ impl Interpreter {
             _ => Err(format!("[line {}] Unsupported unary operator", operator.line).into())
         }
     }
-}
\ No newline at end of file
+}
+
+/// 检查实参个数是否在函数接受的范围内。
+pub(crate) fn check_arity(line: usize, name: &str, func: &dyn Callable, got: usize) -> Result<(), RuntimeError> {
+    let (min, max) = (func.min_arity(), func.max_arity());
+    if got < min || max.is_some_and(|max| got > max) {
+        return Err(RuntimeError::ArityError { line, name: name.to_string(), min, max, got });
+    }
+    Ok(())
+}
+
+/// 把静态解析错误合并成一个 `RuntimeError::Error`，每行一条错误信息。
+fn join_diagnostics(diagnostics: Vec<Diagnostic>) -> RuntimeError {
+    diagnostics.iter().map(|d| d.to_string()).collect::<Vec<_>>().join("\n").into()
+}
+
+/// 二元运算的两个数值操作数：都是整数时保持整数，否则都提升为浮点数。
+enum Numbers {
+    Ints(i64, i64),
+    Floats(f64, f64),
+}
+
+/// 取出两个数值操作数，任一侧不是数字时返回 None。
+fn numbers(left: &MskValue, right: &MskValue) -> Option<Numbers> {
+    match (left, right) {
+        (MskValue::Int(l), MskValue::Int(r)) => Some(Numbers::Ints(*l, *r)),
+        _ => Some(Numbers::Floats(left.as_f64()?, right.as_f64()?)),
+    }
+}
+
+/// 整数运算结果精确（没有溢出）时保持整数，否则退回到浮点数结果。
+fn int_or_float(exact: Option<i64>, approx: f64) -> MskValue {
+    match exact {
+        Some(n) => MskValue::Int(n),
+        None => MskValue::Float(approx),
+    }
+}
+
+/// 取出被下标访问的列表，其他类型的值不能使用 `[]`。
+fn list_operand<'v>(bracket: &Token, object: &'v MskValue) -> Result<&'v Rc<RefCell<Vec<MskValue>>>, RuntimeError> {
+    match object {
+        MskValue::List(items) => Ok(items),
+        _ => Err(RuntimeError::TypeError {
+            line: bracket.line,
+            message: format!("Only lists can be indexed but got {}.", describe_operand(object)),
+        }),
+    }
+}
+
+/// 将下标值转换为列表位置。下标必须是整数（或没有小数部分的浮点数）；负数下标从末尾开始计数，
+/// `-1` 是最后一个元素。换算后仍不在 `0..len` 内时报告越界，错误中给出原始下标。
+fn list_index(bracket: &Token, index: &MskValue, len: usize) -> Result<usize, RuntimeError> {
+    let i = match index {
+        MskValue::Int(i) => *i,
+        MskValue::Float(n) if n.fract() == 0.0 => *n as i64,
+        _ => {
+            return Err(RuntimeError::TypeError {
+                line: bracket.line,
+                message: format!("List index must be an integer but got {}.", describe_operand(index)),
+            })
+        }
+    };
+    let position = if i < 0 { i + len as i64 } else { i };
+    if position < 0 || position as usize >= len {
+        return Err(RuntimeError::IndexError { line: bracket.line, index: i });
+    }
+    Ok(position as usize)
+}
+
+/// `min=` / `max=` 的计算：直接使用 `min` / `max` 原生函数的实现，不受同名变量遮蔽的影响。
+fn min_max(operator: &Token, current: &MskValue, value: &MskValue) -> Result<MskValue, RuntimeError> {
+    let result = match operator.lexeme.as_str() {
+        "min" => min_value(current, value),
+        _ => max_value(current, value),
+    };
+    result.ok_or_else(|| operand_error(operator, "numbers", current, value))
+}
+
+/// 构造二元运算的类型错误，消息中带上两个操作数的值和类型，例如
+/// `'-' expected numbers but got "x" (string) and 1 (number).`
+fn operand_error(operator: &Token, expected: &str, left: &MskValue, right: &MskValue) -> RuntimeError {
+    RuntimeError::TypeError {
+        line: operator.line,
+        message: format!(
+            "'{}' expected {} but got {} and {}.",
+            operator.lexeme,
+            expected,
+            describe_operand(left),
+            describe_operand(right)
+        ),
+    }
+}
+
+/// 以 `值 (类型)` 的形式描述一个操作数，字符串带引号以便与数字区分。
+fn describe_operand(value: &MskValue) -> String {
+    match value {
+        MskValue::String(s) => format!("\"{}\" ({})", s, value.type_name()),
+        _ => format!("{} ({})", value, value.type_name()),
+    }
+}
@@ -0,0 +1,47 @@
+//! lib.rs - 解释器的库入口。
+//! 暴露扫描、解析和执行各阶段的类型，其他 Rust 程序可以依赖本 crate 直接嵌入解释器；
+//! `main.rs` 只是基于这些接口的命令行前端。
+
+pub mod token;
+pub mod scanner;
+pub mod parser;
+pub mod ast;
+pub mod msk_value;
+pub mod interpreter;
+pub mod environment;
+pub mod control_flow;
+pub mod callable;
+pub mod class;
+pub mod native_fun;
+pub mod user_fun;
+pub mod diagnostic;
+pub mod repl;
+pub mod resolver;
+
+pub use interpreter::{Interpreter, RuntimeError};
+pub use msk_value::MskValue;
+pub use parser::Parser;
+pub use scanner::Scanner;
+
+
+/// 扫描、解析并在一个新的解释器中执行一段源码。
+/// 如果最后一条语句是表达式语句，返回它的值，否则返回 nil（见 `Interpreter::run_program`）。
+/// 扫描和解析错误会合并成一个 `RuntimeError::Error`，每行一条错误信息。
+/// 使用库默认的最大调用层数 `interpreter::DEFAULT_MAX_CALL_DEPTH`（200 层）；需要更深的递归时请自行创建
+/// `Interpreter` 并调用 `set_max_call_depth`。
+pub fn run_source(src: &str) -> Result<MskValue, RuntimeError> {
+    let (tokens, diagnostics) = Scanner::new(src).scan_tokens_with_diagnostics();
+    let stmts = match Parser::new(tokens).parse_with_diagnostics() {
+        Ok(stmts) if diagnostics.is_empty() => stmts,
+        Ok(_) => return Err(join_diagnostics(&diagnostics).into()),
+        Err(parse_diagnostics) => {
+            let all: Vec<_> = diagnostics.into_iter().chain(parse_diagnostics).collect();
+            return Err(join_diagnostics(&all).into());
+        }
+    };
+    Interpreter::new().run_program(&stmts)
+}
+
+fn join_diagnostics(diagnostics: &[diagnostic::Diagnostic]) -> String {
+    diagnostics.iter().map(|d| d.to_string()).collect::<Vec<_>>().join("\n")
+}
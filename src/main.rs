@@ -1,19 +1,6 @@
 //! main.rs - 解释器的主入口文件。
 //! 负责处理命令行参数、读取文件，并协调 Scanner 和 Parser 的工作。
 
-// 声明并引入项目内的模块，以便在 main.rs 中使用它们。
-mod token;
-mod scanner;
-mod parser;
-mod ast;
-mod msk_value;
-mod interpreter;
-mod environment;
-mod control_flow;
-mod callable;
-mod native_fun;
-mod user_fun;
-
 use std::env;
 // 用于处理命令行参数
 use std::fs;
@@ -24,25 +11,80 @@ use std::process::exit;
 use log::info;
 // 用于以特定的退出码终止程序
 
-use crate::interpreter::RuntimeError;
-use parser::Parser;
-// 从我们自己的模块中导入所需的结构体。
-use scanner::Scanner;
+// 从解释器库中导入所需的结构体。
+use codecrafters_interpreter::diagnostic::Diagnostic;
+use codecrafters_interpreter::resolver::Resolver;
+use codecrafters_interpreter::{interpreter, repl, Parser, RuntimeError, Scanner};
+
+/// 运行 CLI 的线程栈大小。解释器以递归方式求值，调试构建下每层 MskLang 调用占用较多原生栈，
+/// 需要足够大的栈才能在达到最大调用层数时报告错误，而不是直接栈溢出崩溃。
+const CLI_STACK_SIZE: usize = 256 * 1024 * 1024;
 
-/// 程序的主函数。
+/// CLI 允许的最大调用层数。CLI 线程的栈足够大，可以比库的默认值 `DEFAULT_MAX_CALL_DEPTH` 更深。
+const CLI_MAX_CALL_DEPTH: usize = 1000;
+
+/// 程序的主函数：在栈更大的线程上运行 CLI。
 fn main() {
+    let cli = std::thread::Builder::new()
+        .stack_size(CLI_STACK_SIZE)
+        .spawn(run_cli)
+        .expect("failed to spawn interpreter thread");
+    if cli.join().is_err() {
+        exit(101);
+    }
+}
+
+/// 创建 CLI 使用的解释器。
+fn cli_interpreter() -> interpreter::Interpreter {
+    let mut interpreter = interpreter::Interpreter::new();
+    interpreter.set_max_call_depth(CLI_MAX_CALL_DEPTH);
+    interpreter
+}
+
+/// 处理命令行参数并执行对应的命令。
+fn run_cli() {
     std::env::set_var("RUST_LOG", "info");
     pretty_env_logger::init();
     // 收集命令行参数。
     let args: Vec<String> = env::args().collect();
+    // 不带参数或使用 `repl` 命令时进入交互模式
+    if args.len() == 1 || args[1] == "repl" {
+        repl::run_repl_with(&mut cli_interpreter(), io::stdin().lock());
+        exit(0);
+    }
+    // 命令之后、脚本路径之前的参数是解释器选项；`--` 结束选项，之后的第一个参数总是脚本路径
+    let mut asi = false;
+    let mut warn_recursion = false;
+    let mut rest = args.iter().skip(2);
+    let mut filename = None;
+    for arg in rest.by_ref() {
+        match arg.as_str() {
+            // `--asi`：允许用换行代替语句末尾的分号
+            "--asi" => asi = true,
+            // `--warn-recursion`：对可能导致栈溢出的非尾部递归调用发出警告
+            "--warn-recursion" => warn_recursion = true,
+            "--" => {
+                filename = rest.next();
+                break;
+            }
+            option if option.starts_with("--") => {
+                eprintln!("Unknown option: {}", option);
+                exit(64);
+            }
+            _ => {
+                filename = Some(arg);
+                break;
+            }
+        }
+    }
     // 需要至少两个参数：命令（如 `parse`）和文件名。
-    if args.len() < 3 {
+    let (Some(command), Some(filename)) = (args.get(1), filename) else {
         // 如果参数不足，向标准错误输出用法信息。
-        writeln!(io::stderr(), "Usage: {} <command> <filename>", args[0]).unwrap();
+        writeln!(io::stderr(), "Usage: {} <command> [--asi] [--warn-recursion] [--] <filename> [args...]\n       {} [repl]", args[0], args[0]).unwrap();
         return;
-    }
-    let command = &args[1];
-    let filename = &args[2];
+    };
+    // 脚本路径之后的参数原样传给脚本，通过 `argv()` 读取
+    let script_args: Vec<String> = rest.cloned().collect();
     let mut had_error = false;
     let mut interpreter_error = false;
     // 读取指定文件的内容。
@@ -64,6 +106,14 @@ fn main() {
                 println!("{}", token);
             }
         }
+        "tokenize-json" => {
+            let scanner = Scanner::new(&file_contents);
+            let (tokens, error) = scanner.scan_tokens();
+            had_error = error;
+            // 以 JSON 数组的形式输出全部 Token
+            let json: Vec<String> = tokens.iter().map(|token| token.to_json()).collect();
+            println!("[{}]", json.join(","));
+        }
         "parse" => {
             // 1. 扫描阶段
             let scanner = Scanner::new(&file_contents);
@@ -98,14 +148,17 @@ fn main() {
             // 3. 解释阶段
             if !had_error {
                 if let Some(expr) = expr_option {
-                    let mut interpreter = interpreter::Interpreter::new();
-                    match interpreter.evaluate(&expr) {
+                    if let Err(diagnostics) = Resolver::new().resolve_expression(&expr) {
+                        report(&diagnostics);
+                        exit(65);
+                    }
+                    let mut interpreter = cli_interpreter();
+                    match interpreter.eval_expr(&expr).map_err(RuntimeError::into_stray_error) {
                         Ok(value) => println!("{}", value),
-                        Err(RuntimeError::Error(e)) => {
+                        Err(e) => {
                             writeln!(io::stderr(), "Runtime error: {}", e).unwrap();
                             interpreter_error = true;
                         },
-                        _=>{}
                     }
                 }
             }
@@ -116,17 +169,32 @@ fn main() {
             let (tokens, had_scanner_error) = scanner.scan_tokens();
             // 2. 解析阶段
             let mut parser = Parser::new(tokens);
+            parser.set_asi(asi);
             let (stmts_option, had_parser_error) = parser.parse();
             // 检查在任何阶段是否发生了错误
             had_error = had_scanner_error || had_parser_error;
 
-            // 3. 执行阶段
+            // 4. 执行阶段
             if !had_error {
                 if let Some(stmts) = stmts_option {
-                    let mut interpreter = interpreter::Interpreter::new();
-                    if let Err(RuntimeError::Error(e)) = interpreter.interpret(stmts.as_slice()) {
-                        writeln!(io::stderr(), "Runtime error: {}", e).unwrap();
-                        interpreter_error = true;
+                    let mut interpreter = cli_interpreter();
+                    interpreter.set_args(script_args);
+                    // 3. 静态解析阶段
+                    let mut resolver = Resolver::new();
+                    resolver.set_warn_recursion(warn_recursion);
+                    resolver.set_known_globals(interpreter.global_names());
+                    let resolved = resolver.resolve(&stmts);
+                    report(resolver.warnings());
+                    if let Err(diagnostics) = resolved {
+                        report(&diagnostics);
+                        exit(65);
+                    }
+                    match interpreter.run_program(&stmts) {
+                        Ok(_) => {}
+                        Err(e) => {
+                            writeln!(io::stderr(), "Runtime error: {}", e).unwrap();
+                            interpreter_error = true;
+                        }
                     }
                 }
             }
@@ -148,3 +216,10 @@ fn main() {
         exit(0);
     }
 }
+
+/// 把收集到的错误逐行输出到 stderr。
+fn report(diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        writeln!(io::stderr(), "{}", diagnostic).unwrap();
+    }
+}
@@ -1,16 +1,28 @@
+use std::cell::RefCell;
 use std::rc::Rc;
 use crate::callable::Callable;
+use crate::class::{MskClass, MskInstance};
 use std::fmt::{Debug, Display, Formatter};
 
 #[derive(Clone)]
 pub enum MskValue {
     // 一个浮点数值。
     Float(f64),
+    /// 一个整数值，由不带小数点的字面量产生，运算结果精确时保持为整数。
+    Int(i64),
     /// 一个布尔值，表示真或假。
     Boolean(bool),
     /// 一个字符串值。
     String(String),
     Callable(Rc<dyn Callable>),
+    /// 可变的字符串缓冲区，由 `sb_new` 创建，用于高效地拼接大量文本。
+    StringBuilder(Rc<RefCell<String>>),
+    /// 列表，多个引用共享同一份元素，`a[0] = 1` 会修改所有引用看到的内容。
+    List(Rc<RefCell<Vec<MskValue>>>),
+    /// 由 `class` 声明产生的类，调用它会创建实例。
+    Class(Rc<MskClass>),
+    /// 类的实例，多个引用共享同一份字段。
+    Instance(Rc<RefCell<MskInstance>>),
     Nil,
 }
 
@@ -18,9 +30,23 @@ impl Display for MskValue {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             MskValue::Float(n) => write!(f, "{}", n),
+            MskValue::Int(n) => write!(f, "{}", n),
             MskValue::Boolean(b) => write!(f, "{}", b),
             MskValue::String(s) => write!(f, "{}", s),
             MskValue::Callable(_) => write!(f, "<fn>"),
+            MskValue::StringBuilder(_) => write!(f, "<string builder>"),
+            MskValue::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            MskValue::Class(class) => write!(f, "{}", class.name),
+            MskValue::Instance(instance) => write!(f, "{} instance", instance.borrow().class.name),
             MskValue::Nil => write!(f, "nil"),
         }
     }
@@ -29,19 +55,46 @@ impl Debug for MskValue {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             MskValue::Float(n) => write!(f, "Float({})", n),
+            MskValue::Int(n) => write!(f, "Int({})", n),
             MskValue::Boolean(b) => write!(f, "Boolean({})", b),
             MskValue::String(s) => write!(f, "String({})", s),
             MskValue::Callable(_) => write!(f, "<fn>"),
+            MskValue::StringBuilder(sb) => write!(f, "StringBuilder({})", sb.borrow()),
+            MskValue::List(items) => write!(f, "List({:?})", items.borrow()),
+            MskValue::Class(class) => write!(f, "Class({})", class.name),
+            MskValue::Instance(instance) => write!(f, "Instance({})", instance.borrow().class.name),
             MskValue::Nil => write!(f, "Nil"),
         }
     }
 }
 impl MskValue {
+    /// 值的类型名称，用于错误信息等面向用户的输出。
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            MskValue::Float(_) | MskValue::Int(_) => "number",
+            MskValue::Boolean(_) => "boolean",
+            MskValue::String(_) => "string",
+            MskValue::Callable(_) => "function",
+            MskValue::StringBuilder(_) => "stringbuilder",
+            MskValue::List(_) => "list",
+            MskValue::Class(_) => "class",
+            MskValue::Instance(_) => "instance",
+            MskValue::Nil => "nil",
+        }
+    }
+    /// 将数值（整数或浮点数）转换为 f64，其他类型返回 None。
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            MskValue::Float(n) => Some(*n),
+            MskValue::Int(n) => Some(*n as f64),
+            _ => None,
+        }
+    }
     pub fn is_true(&self) -> bool {
         match self {
             MskValue::Boolean(b) => *b,
             MskValue::Nil => false,
-            _ => true, // 非 Nil 和 Boolean 的值都视为 true
+            _ => true, // 非 Nil 和 Boolean 的值都视为 true，包括整数 0 和浮点数 0.0
         }
     }
 }
\ No newline at end of file
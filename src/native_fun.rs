@@ -1,21 +1,1135 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
 use crate::callable::Callable;
 use crate::interpreter::{Interpreter, RuntimeError};
 use crate::msk_value::MskValue;
 
+#[derive(Default)]
 pub struct ClockNative;
 impl Callable for ClockNative {
     fn arity(&self) -> usize { 0 }
-    fn call(&self, _interpreter: &mut Interpreter, _args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
-        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs_f64().round();
+    fn call(&self, interpreter: &mut Interpreter, _args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let now = interpreter.now().as_secs_f64().round();
         Ok(MskValue::Float(now))
     }
 }
-impl Default for ClockNative {
-    fn default() -> Self {
-        ClockNative {}
+
+/// 返回当前 UTC 时间的 ISO-8601 字符串，例如 `2024-05-01T12:30:00Z`。
+/// 时间来源与 `clock` 相同，都经过解释器的时钟，便于替换。
+#[derive(Default)]
+pub struct NowIsoNative;
+impl Callable for NowIsoNative {
+    fn arity(&self) -> usize { 0 }
+    fn call(&self, interpreter: &mut Interpreter, _args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        Ok(MskValue::String(format_iso8601(interpreter.now().as_secs())))
+    }
+}
+
+/// `min(a, ...)`：返回参数中最小的数，至少需要一个参数，可以配合展开使用，例如 `min(...list)`。
+#[derive(Default)]
+pub struct MinNative;
+impl Callable for MinNative {
+    fn arity(&self) -> usize { 1 }
+    fn max_arity(&self) -> Option<usize> { None }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        fold_numbers(&args, min_value).ok_or_else(|| "min() expects numbers.".to_string().into())
+    }
+}
+
+/// `max(a, ...)`：返回参数中最大的数，规则与 `min` 相同。
+#[derive(Default)]
+pub struct MaxNative;
+impl Callable for MaxNative {
+    fn arity(&self) -> usize { 1 }
+    fn max_arity(&self) -> Option<usize> { None }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        fold_numbers(&args, max_value).ok_or_else(|| "max() expects numbers.".to_string().into())
+    }
+}
+
+/// 用 `pick` 两两合并全部参数，任一参数不是数字时返回 None。
+fn fold_numbers(args: &[MskValue], pick: fn(&MskValue, &MskValue) -> Option<MskValue>) -> Option<MskValue> {
+    let (first, rest) = args.split_first()?;
+    first.as_f64()?;
+    rest.iter().try_fold(first.clone(), |acc, value| pick(&acc, value))
+}
+
+/// 两个数中较小的一个，都是整数时结果仍是整数。任一侧不是数字时返回 None。
+/// `min` 和 `x min= v` 共用这个实现。
+pub(crate) fn min_value(a: &MskValue, b: &MskValue) -> Option<MskValue> {
+    match (a, b) {
+        (MskValue::Int(a), MskValue::Int(b)) => Some(MskValue::Int(*a.min(b))),
+        _ => Some(MskValue::Float(a.as_f64()?.min(b.as_f64()?))),
+    }
+}
+
+/// 两个数中较大的一个，规则与 `min_value` 相同。
+pub(crate) fn max_value(a: &MskValue, b: &MskValue) -> Option<MskValue> {
+    match (a, b) {
+        (MskValue::Int(a), MskValue::Int(b)) => Some(MskValue::Int(*a.max(b))),
+        _ => Some(MskValue::Float(a.as_f64()?.max(b.as_f64()?))),
+    }
+}
+
+/// `sqrt(x)`：平方根。负数没有实数平方根，直接报错而不是返回 NaN。
+#[derive(Default)]
+pub struct SqrtNative;
+impl Callable for SqrtNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match args[0].as_f64() {
+            Some(n) if n < 0.0 => Err(format!("sqrt() of negative number {}.", n).into()),
+            Some(n) => Ok(MskValue::Float(n.sqrt())),
+            None => Err("sqrt() expects a number.".to_string().into()),
+        }
+    }
+}
+
+/// `abs(x)`：绝对值，整数保持为整数。
+#[derive(Default)]
+pub struct AbsNative;
+impl Callable for AbsNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match &args[0] {
+            // i64::MIN 的绝对值超出 i64 范围，退回到浮点数
+            MskValue::Int(n) => Ok(n.checked_abs().map_or(MskValue::Float((*n as f64).abs()), MskValue::Int)),
+            MskValue::Float(n) => Ok(MskValue::Float(n.abs())),
+            _ => Err("abs() expects a number.".to_string().into()),
+        }
+    }
+}
+
+/// `floor(x)`：向下取整，整数原样返回。
+#[derive(Default)]
+pub struct FloorNative;
+impl Callable for FloorNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match &args[0] {
+            MskValue::Int(n) => Ok(MskValue::Int(*n)),
+            MskValue::Float(n) => Ok(MskValue::Float(n.floor())),
+            _ => Err("floor() expects a number.".to_string().into()),
+        }
+    }
+}
+
+/// `ceil(x)`：向上取整，整数原样返回。
+#[derive(Default)]
+pub struct CeilNative;
+impl Callable for CeilNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match &args[0] {
+            MskValue::Int(n) => Ok(MskValue::Int(*n)),
+            MskValue::Float(n) => Ok(MskValue::Float(n.ceil())),
+            _ => Err("ceil() expects a number.".to_string().into()),
+        }
+    }
+}
+
+/// `round(x)`：四舍五入到最近的整数，`.5` 远离零舍入，整数原样返回。
+#[derive(Default)]
+pub struct RoundNative;
+impl Callable for RoundNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match &args[0] {
+            MskValue::Int(n) => Ok(MskValue::Int(*n)),
+            MskValue::Float(n) => Ok(MskValue::Float(n.round())),
+            _ => Err("round() expects a number.".to_string().into()),
+        }
+    }
+}
+
+/// `pow(base, exp)`：乘方。整数的非负整数次幂在不溢出时保持为整数，其余情况得到浮点数。
+#[derive(Default)]
+pub struct PowNative;
+impl Callable for PowNative {
+    fn arity(&self) -> usize { 2 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        if let (MskValue::Int(base), MskValue::Int(exp)) = (&args[0], &args[1]) {
+            if let Some(n) = u32::try_from(*exp).ok().and_then(|exp| base.checked_pow(exp)) {
+                return Ok(MskValue::Int(n));
+            }
+        }
+        match (args[0].as_f64(), args[1].as_f64()) {
+            (Some(base), Some(exp)) => Ok(MskValue::Float(base.powf(exp))),
+            _ => Err("pow() expects two numbers.".to_string().into()),
+        }
+    }
+}
+
+/// 按向下取整除法求余，结果符号与除数相同（与 Python 一致）：`floor_mod(-7, 3)` 为 2，而 `-7 % 3` 为 -1。
+#[derive(Default)]
+pub struct FloorModNative;
+impl Callable for FloorModNative {
+    fn arity(&self) -> usize { 2 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match (&args[0], &args[1]) {
+            (MskValue::Int(_), MskValue::Int(0)) => Err("floor_mod() divisor must not be zero.".to_string().into()),
+            // i64::MIN 对 -1 取余会溢出，但向下取整的余数必然是 0
+            (MskValue::Int(a), MskValue::Int(b)) => {
+                let r = a.checked_rem(*b).unwrap_or(0);
+                if r != 0 && (r < 0) != (*b < 0) {
+                    Ok(MskValue::Int(r + b))
+                } else {
+                    Ok(MskValue::Int(r))
+                }
+            }
+            (a, b) => match (a.as_f64(), b.as_f64()) {
+                (Some(_), Some(0.0)) => Err("floor_mod() divisor must not be zero.".to_string().into()),
+                (Some(a), Some(b)) => {
+                    let r = a % b;
+                    // 余数与除数异号时再补上一个除数，把截断除法的结果换成向下取整除法的结果
+                    if r != 0.0 && (r < 0.0) != (b < 0.0) {
+                        Ok(MskValue::Float(r + b))
+                    } else {
+                        Ok(MskValue::Float(r))
+                    }
+                }
+                _ => Err("floor_mod() expects two numbers.".to_string().into()),
+            },
+        }
+    }
+}
+
+/// `is_nan(x)`：判断数字是否为 NaN，例如 `0 / 0.0` 之类运算的结果。
+#[derive(Default)]
+pub struct IsNanNative;
+impl Callable for IsNanNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match args[0].as_f64() {
+            Some(n) => Ok(MskValue::Boolean(n.is_nan())),
+            None => Err("is_nan() expects a number.".to_string().into()),
+        }
+    }
+}
+
+/// `is_finite(x)`：判断数字既不是无穷大也不是 NaN，整数总是有限的。
+#[derive(Default)]
+pub struct IsFiniteNative;
+impl Callable for IsFiniteNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match args[0].as_f64() {
+            Some(n) => Ok(MskValue::Boolean(n.is_finite())),
+            None => Err("is_finite() expects a number.".to_string().into()),
+        }
+    }
+}
+
+/// `idiv(a, b)`：整数除法，向零截断，与 `%` 的余数配套：`idiv(-7, 3)` 为 -2。
+/// `/` 总是得到浮点数，需要整数商时使用它。
+#[derive(Default)]
+pub struct IdivNative;
+impl Callable for IdivNative {
+    fn arity(&self) -> usize { 2 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match (&args[0], &args[1]) {
+            (MskValue::Int(_), MskValue::Int(0)) => Err("idiv() divisor must not be zero.".to_string().into()),
+            (MskValue::Int(a), MskValue::Int(b)) => match a.checked_div(*b) {
+                Some(q) => Ok(MskValue::Int(q)),
+                None => Ok(MskValue::Float(-(*a as f64))),
+            },
+            _ => Err("idiv() expects two integers.".to_string().into()),
+        }
+    }
+}
+
+/// `to_hex(n)`：返回整数的十六进制表示，例如 `to_hex(255)` 为 `"0xff"`，负数带前导 `-`。
+#[derive(Default)]
+pub struct ToHexNative;
+impl Callable for ToHexNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match whole_number(&args[0]) {
+            Some(n) if n < 0 => Ok(MskValue::String(format!("-0x{:x}", n.unsigned_abs()))),
+            Some(n) => Ok(MskValue::String(format!("0x{:x}", n))),
+            None => Err("to_hex() expects an integer.".to_string().into()),
+        }
+    }
+}
+
+/// `to_bin(n)`：返回整数的二进制表示，例如 `to_bin(5)` 为 `"0b101"`，负数带前导 `-`。
+#[derive(Default)]
+pub struct ToBinNative;
+impl Callable for ToBinNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match whole_number(&args[0]) {
+            Some(n) if n < 0 => Ok(MskValue::String(format!("-0b{:b}", n.unsigned_abs()))),
+            Some(n) => Ok(MskValue::String(format!("0b{:b}", n))),
+            None => Err("to_bin() expects an integer.".to_string().into()),
+        }
+    }
+}
+
+/// `group_digits(n)`：在整数部分每三位插入一个逗号，例如 `group_digits(-1234567.5)` 为 `"-1,234,567.5"`。
+/// 小数部分保持原样。
+#[derive(Default)]
+pub struct GroupDigitsNative;
+impl Callable for GroupDigitsNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let text = match &args[0] {
+            MskValue::Int(_) | MskValue::Float(_) => args[0].to_string(),
+            _ => return Err("group_digits() expects a number.".to_string().into()),
+        };
+        let (sign, unsigned) = match text.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", text.as_str()),
+        };
+        let (integer, fraction) = match unsigned.find('.') {
+            Some(dot) => unsigned.split_at(dot),
+            None => (unsigned, ""),
+        };
+        // inf、NaN 等没有数字可分组，原样返回
+        if !integer.bytes().all(|b| b.is_ascii_digit()) {
+            return Ok(MskValue::String(text));
+        }
+        let mut grouped = String::with_capacity(integer.len() + integer.len() / 3);
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                grouped.push(',');
+            }
+            grouped.push(digit);
+        }
+        Ok(MskValue::String(format!("{}{}{}", sign, grouped, fraction)))
+    }
+}
+
+/// 整数，或没有小数部分且在 i64 范围内的浮点数，转换为 i64。
+fn whole_number(value: &MskValue) -> Option<i64> {
+    match value {
+        MskValue::Int(n) => Some(*n),
+        MskValue::Float(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => Some(*n as i64),
+        _ => None,
+    }
+}
+
+/// `assert(cond)` / `assert(cond, message)`：条件为假时报告运行时错误，通过时返回 nil。
+/// `message` 可以是无参函数，只在断言失败时才调用它生成消息，避免白白构造昂贵的消息。
+#[derive(Default)]
+pub struct AssertNative;
+impl Callable for AssertNative {
+    fn arity(&self) -> usize { 1 }
+    fn max_arity(&self) -> Option<usize> { Some(2) }
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        if args[0].is_true() {
+            return Ok(MskValue::Nil);
+        }
+        let message = match args.get(1) {
+            None => return Err("assert() failed.".to_string().into()),
+            Some(MskValue::Callable(f)) => interpreter.call_back(f, Vec::new())?,
+            Some(message) => message.clone(),
+        };
+        Err(format!("assert() failed: {}", message).into())
+    }
+}
+
+/// `assert_approx` 省略 epsilon 时允许的最大误差。
+const DEFAULT_EPSILON: f64 = 1e-9;
+
+/// `assert_approx(a, b)` / `assert_approx(a, b, epsilon)`：断言两个数之差的绝对值不超过 epsilon，
+/// 通过时返回 nil，否则报告实际差值。省略 epsilon 时使用 `DEFAULT_EPSILON`。
+#[derive(Default)]
+pub struct AssertApproxNative;
+impl Callable for AssertApproxNative {
+    fn arity(&self) -> usize { 2 }
+    fn max_arity(&self) -> Option<usize> { Some(3) }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let epsilon = match args.get(2) {
+            None => DEFAULT_EPSILON,
+            Some(value) => match value.as_f64() {
+                Some(epsilon) if epsilon >= 0.0 => epsilon,
+                _ => return Err("assert_approx() expects a non-negative epsilon.".to_string().into()),
+            },
+        };
+        let (a, b) = match (args[0].as_f64(), args[1].as_f64()) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return Err("assert_approx() expects two numbers.".to_string().into()),
+        };
+        let diff = (a - b).abs();
+        if diff <= epsilon {
+            Ok(MskValue::Nil)
+        } else {
+            Err(format!("assert_approx() failed: {} and {} differ by {} (epsilon {}).", a, b, diff, epsilon).into())
+        }
+    }
+}
+
+/// `read_line()`：从 stdin 读取一行，去掉末尾的换行符后返回；输入结束（EOF）时返回 nil。
+/// 调用会阻塞，直到读到一整行或输入结束。
+#[derive(Default)]
+pub struct ReadLineNative;
+impl Callable for ReadLineNative {
+    fn arity(&self) -> usize { 0 }
+    fn call(&self, _interpreter: &mut Interpreter, _args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let mut line = String::new();
+        match std::io::stdin().read_line(&mut line) {
+            Ok(0) => Ok(MskValue::Nil),
+            Ok(_) => {
+                if line.ends_with('\n') {
+                    line.pop();
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                }
+                Ok(MskValue::String(line))
+            }
+            Err(e) => Err(format!("read_line() failed: {}.", e).into()),
+        }
+    }
+}
+
+/// 将参数的显示形式加换行输出到解释器的错误输出（默认是 stderr），返回 nil。
+/// 用于把诊断信息和 `print` 输出的数据分开。
+#[derive(Default)]
+pub struct EprintNative;
+impl Callable for EprintNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        interpreter.write_error_line(&args[0].to_string())?;
+        Ok(MskValue::Nil)
+    }
+}
+
+/// `pretty_print(x)`：像 `print` 一样输出一个值，但列表会展开成多行，每层缩进两个空格，返回 nil。
+/// 列表直接或间接包含自身时，重复出现的部分输出为 `[...]`。
+#[derive(Default)]
+pub struct PrettyPrintNative;
+impl Callable for PrettyPrintNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let mut text = String::new();
+        pretty(&args[0], 0, &mut Vec::new(), &mut text);
+        interpreter.write_output_line(&text)?;
+        Ok(MskValue::Nil)
+    }
+}
+
+/// 把 `value` 的多行形式追加到 `out`，`depth` 是当前的缩进层数，`open` 是正在展开的外层列表。
+fn pretty(value: &MskValue, depth: usize, open: &mut Vec<*const RefCell<Vec<MskValue>>>, out: &mut String) {
+    let MskValue::List(items) = value else {
+        out.push_str(&value.to_string());
+        return;
+    };
+    let items_ptr = Rc::as_ptr(items);
+    if open.contains(&items_ptr) {
+        out.push_str("[...]");
+        return;
+    }
+    let items = items.borrow();
+    if items.is_empty() {
+        out.push_str("[]");
+        return;
+    }
+    open.push(items_ptr);
+    out.push_str("[\n");
+    for (i, item) in items.iter().enumerate() {
+        out.push_str(&"  ".repeat(depth + 1));
+        pretty(item, depth + 1, open, out);
+        out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+    }
+    out.push_str(&"  ".repeat(depth));
+    out.push(']');
+    open.pop();
+}
+
+/// `repr(x)`：返回 `x` 的源码形式。字符串带引号并转义，把结果作为字符串字面量重新扫描会得到原来的字符串；
+/// 列表里的元素同样使用源码形式，其他值与 `print` 的输出相同。
+#[derive(Default)]
+pub struct ReprNative;
+impl Callable for ReprNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let mut text = String::new();
+        repr(&args[0], &mut Vec::new(), &mut text);
+        Ok(MskValue::String(text))
+    }
+}
+
+/// 把 `value` 的源码形式追加到 `out`，`open` 的用法与 `pretty` 相同。
+fn repr(value: &MskValue, open: &mut Vec<*const RefCell<Vec<MskValue>>>, out: &mut String) {
+    match value {
+        MskValue::String(s) => {
+            out.push('"');
+            for c in s.chars() {
+                match c {
+                    '"' => out.push_str("\\\""),
+                    '\\' => out.push_str("\\\\"),
+                    '\n' => out.push_str("\\n"),
+                    '\t' => out.push_str("\\t"),
+                    '\r' => out.push_str("\\r"),
+                    '\0' => out.push_str("\\0"),
+                    c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+                    c => out.push(c),
+                }
+            }
+            out.push('"');
+        }
+        MskValue::List(items) => {
+            let items_ptr = Rc::as_ptr(items);
+            if open.contains(&items_ptr) {
+                out.push_str("[...]");
+                return;
+            }
+            open.push(items_ptr);
+            out.push('[');
+            for (i, item) in items.borrow().iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                repr(item, open, out);
+            }
+            out.push(']');
+            open.pop();
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+/// `memoize(f)`：返回一个包装了 `f` 的函数，相同参数的调用结果会被缓存。
+#[derive(Default)]
+pub struct MemoizeNative;
+impl Callable for MemoizeNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match &args[0] {
+            MskValue::Callable(inner) => Ok(MskValue::Callable(Rc::new(MemoizedFunction {
+                inner: inner.clone(),
+                cache: RefCell::new(HashMap::new()),
+            }))),
+            _ => Err("memoize() expects a function.".to_string().into()),
+        }
+    }
+}
+
+/// 带缓存的函数：以参数的结构化形式为键，未命中时才调用被包装的函数。
+pub struct MemoizedFunction {
+    inner: Rc<dyn Callable>,
+    cache: RefCell<HashMap<String, MskValue>>,
+}
+impl Callable for MemoizedFunction {
+    fn arity(&self) -> usize {
+        self.inner.arity()
+    }
+    fn min_arity(&self) -> usize {
+        self.inner.min_arity()
+    }
+    fn max_arity(&self) -> Option<usize> {
+        self.inner.max_arity()
+    }
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let key = args.iter().map(memo_key).collect::<Vec<String>>().join(",");
+        if let Some(value) = self.cache.borrow().get(&key) {
+            return Ok(value.clone());
+        }
+        // 调用期间不持有缓存的借用，被包装的函数可能会再次调用这个记忆化函数
+        let value = self.inner.call(interpreter, args)?;
+        self.cache.borrow_mut().insert(key, value.clone());
+        Ok(value)
+    }
+}
+
+/// 生成参数的缓存键。字符串带引号并转义，因此可以区分 `1` 和 `"1"`，也不会与分隔参数的逗号混淆；
+/// 函数、类、实例以及可变的列表和字符串构建器按指针区分，内容改变后不会命中旧的结果。
+fn memo_key(value: &MskValue) -> String {
+    match value {
+        MskValue::Float(n) => format!("Float({})", n),
+        MskValue::Int(n) => format!("Int({})", n),
+        MskValue::Boolean(b) => format!("Boolean({})", b),
+        MskValue::String(s) => format!("String({:?})", s),
+        MskValue::Nil => "Nil".to_string(),
+        MskValue::Callable(f) => format!("<fn {:p}>", Rc::as_ptr(f) as *const ()),
+        MskValue::StringBuilder(sb) => format!("<string builder {:p}>", Rc::as_ptr(sb)),
+        MskValue::List(items) => format!("<list {:p}>", Rc::as_ptr(items)),
+        MskValue::Class(class) => format!("<class {:p}>", Rc::as_ptr(class)),
+        MskValue::Instance(instance) => format!("<instance {:p}>", Rc::as_ptr(instance)),
+    }
+}
+
+/// `compose(f, g)`：返回新函数 `x => f(g(x))`。
+#[derive(Default)]
+pub struct ComposeNative;
+impl Callable for ComposeNative {
+    fn arity(&self) -> usize { 2 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match (unary_function(&args[0]), unary_function(&args[1])) {
+            (Some(outer), Some(inner)) => Ok(MskValue::Callable(Rc::new(ComposedFunction { first: inner, second: outer }))),
+            _ => Err("compose() expects two functions that take one argument.".to_string().into()),
+        }
+    }
+}
+
+/// `pipe(g, f)`：`compose` 的从左到右版本，返回新函数 `x => f(g(x))`。
+#[derive(Default)]
+pub struct PipeNative;
+impl Callable for PipeNative {
+    fn arity(&self) -> usize { 2 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match (unary_function(&args[0]), unary_function(&args[1])) {
+            (Some(first), Some(second)) => Ok(MskValue::Callable(Rc::new(ComposedFunction { first, second }))),
+            _ => Err("pipe() expects two functions that take one argument.".to_string().into()),
+        }
+    }
+}
+
+/// 由 `compose` / `pipe` 产生的函数：先调用 `first`，再把结果传给 `second`。
+pub struct ComposedFunction {
+    first: Rc<dyn Callable>,
+    second: Rc<dyn Callable>,
+}
+impl Callable for ComposedFunction {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let intermediate = self.first.call(interpreter, args)?;
+        self.second.call(interpreter, vec![intermediate])
+    }
+}
+
+/// `curry(f, a)`：偏应用，返回新函数 `(...rest) => f(a, ...rest)`，可以继续对结果 curry。
+#[derive(Default)]
+pub struct CurryNative;
+impl Callable for CurryNative {
+    fn arity(&self) -> usize { 2 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match &args[0] {
+            MskValue::Callable(inner) if inner.max_arity().map_or(true, |max| max >= 1) => {
+                Ok(MskValue::Callable(Rc::new(PartialFunction {
+                    inner: inner.clone(),
+                    bound: vec![args[1].clone()],
+                })))
+            }
+            MskValue::Callable(_) => Err("curry() expects a function that takes at least one argument.".to_string().into()),
+            _ => Err("curry() expects a function.".to_string().into()),
+        }
+    }
+}
+
+/// 由 `curry` 产生的函数：调用时把预先绑定的参数放在实参前面，再调用被包装的函数。
+pub struct PartialFunction {
+    inner: Rc<dyn Callable>,
+    bound: Vec<MskValue>,
+}
+impl Callable for PartialFunction {
+    fn arity(&self) -> usize {
+        self.inner.arity().saturating_sub(self.bound.len())
+    }
+    fn min_arity(&self) -> usize {
+        self.inner.min_arity().saturating_sub(self.bound.len())
+    }
+    fn max_arity(&self) -> Option<usize> {
+        self.inner.max_arity().map(|max| max.saturating_sub(self.bound.len()))
+    }
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let mut all = self.bound.clone();
+        all.extend(args);
+        self.inner.call(interpreter, all)
+    }
+}
+
+/// 取出能以单个参数调用的函数，用于组合。
+fn unary_function(value: &MskValue) -> Option<Rc<dyn Callable>> {
+    match value {
+        MskValue::Callable(f) if f.min_arity() <= 1 && f.max_arity().map_or(true, |max| max >= 1) => Some(f.clone()),
+        _ => None,
+    }
+}
+
+/// `type(x)`：以字符串形式返回值的类型名称，例如 `"number"`、`"string"`、`"nil"`。
+#[derive(Default)]
+pub struct TypeNative;
+impl Callable for TypeNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        Ok(MskValue::String(args[0].type_name().to_string()))
+    }
+}
+
+/// `num(s)`：把字符串解析为数字，忽略首尾空白。和数字字面量一样，没有小数部分的结果是整数；
+/// 结果必须是有限数，"inf"、"NaN" 以及超出范围的值都会报错。
+#[derive(Default)]
+pub struct NumNative;
+impl Callable for NumNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let s = match &args[0] {
+            MskValue::String(s) => s.trim(),
+            _ => return Err("num() expects a string.".to_string().into()),
+        };
+        if let Ok(n) = s.parse::<i64>() {
+            return Ok(MskValue::Int(n));
+        }
+        // `f64::from_str` 还接受 "inf"、"NaN" 等写法，这些不是 MskLang 的数字，与超出范围的值一起拒绝
+        match s.parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(MskValue::Float(n)),
+            _ => Err(format!("num() could not parse \"{}\" as a number.", s).into()),
+        }
+    }
+}
+
+/// `str(x)`：返回任意值的显示形式，与 `print` 输出的内容相同。
+#[derive(Default)]
+pub struct StrNative;
+impl Callable for StrNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        Ok(MskValue::String(args[0].to_string()))
+    }
+}
+
+/// `arity(f)`：返回函数需要的参数个数。参数个数可变的函数（例如 `env`）返回最少需要的个数。
+#[derive(Default)]
+pub struct ArityNative;
+impl Callable for ArityNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match &args[0] {
+            MskValue::Callable(f) => Ok(MskValue::Int(f.min_arity() as i64)),
+            _ => Err("arity() expects a function.".to_string().into()),
+        }
+    }
+}
+
+/// `len(x)`：返回字符串的字符数或列表的元素个数。
+#[derive(Default)]
+pub struct LenNative;
+impl Callable for LenNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match &args[0] {
+            MskValue::String(s) => Ok(MskValue::Int(s.chars().count() as i64)),
+            MskValue::List(items) => Ok(MskValue::Int(items.borrow().len() as i64)),
+            _ => Err("len() expects a string or list.".to_string().into()),
+        }
+    }
+}
+
+/// `partition(list, pred)`：按 `pred(元素)` 的真假把元素分成两组，返回 `[满足的, 不满足的]`，
+/// 两组都保持原来的顺序。
+#[derive(Default)]
+pub struct PartitionNative;
+impl Callable for PartitionNative {
+    fn arity(&self) -> usize { 2 }
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match (&args[0], &args[1]) {
+            (MskValue::List(items), MskValue::Callable(pred)) => {
+                // 先复制一份元素，回调函数可能会修改原列表
+                let items = items.borrow().clone();
+                let (mut matching, mut rest) = (Vec::new(), Vec::new());
+                for item in items {
+                    if interpreter.call_back(pred, vec![item.clone()])?.is_true() {
+                        matching.push(item);
+                    } else {
+                        rest.push(item);
+                    }
+                }
+                let matching = MskValue::List(Rc::new(RefCell::new(matching)));
+                let rest = MskValue::List(Rc::new(RefCell::new(rest)));
+                Ok(MskValue::List(Rc::new(RefCell::new(vec![matching, rest]))))
+            }
+            _ => Err("partition() expects a list and a function.".to_string().into()),
+        }
+    }
+}
+
+/// `slice(list, start)` / `slice(list, start, end)`：返回下标在 `[start, end)` 内的元素组成的新列表。
+/// 负数下标从末尾开始计数，省略 `end` 表示到末尾；超出范围的下标会被截断到列表两端。
+#[derive(Default)]
+pub struct SliceNative;
+impl Callable for SliceNative {
+    fn arity(&self) -> usize { 2 }
+    fn max_arity(&self) -> Option<usize> { Some(3) }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        // 外层的 None 表示 end 不是整数，内层的 None 表示省略了 end
+        let end = match args.get(2) {
+            None => Some(None),
+            Some(end) => whole_number(end).map(Some),
+        };
+        match (&args[0], whole_number(&args[1]), end) {
+            (MskValue::List(items), Some(start), Some(end)) => {
+                let items = items.borrow();
+                let len = items.len() as i64;
+                let clamp = |i: i64| (if i < 0 { i + len } else { i }).clamp(0, len) as usize;
+                let start = clamp(start);
+                let end = end.map_or(items.len(), clamp);
+                let slice = if start < end { items[start..end].to_vec() } else { Vec::new() };
+                Ok(MskValue::List(Rc::new(RefCell::new(slice))))
+            }
+            _ => Err("slice() expects a list and whole-number bounds.".to_string().into()),
+        }
+    }
+}
+
+/// `reverse(list)`：返回元素顺序相反的新列表，原列表不变。
+#[derive(Default)]
+pub struct ReverseNative;
+impl Callable for ReverseNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match &args[0] {
+            MskValue::List(items) => {
+                let reversed = items.borrow().iter().rev().cloned().collect();
+                Ok(MskValue::List(Rc::new(RefCell::new(reversed))))
+            }
+            other => Err(format!("reverse() expects a list but got {} ({}).", other, other.type_name()).into()),
+        }
+    }
+}
+
+/// `reverse_mut(list)`：原地反转列表，所有引用都会看到新的顺序，返回 nil。
+#[derive(Default)]
+pub struct ReverseMutNative;
+impl Callable for ReverseMutNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match &args[0] {
+            MskValue::List(items) => {
+                items.borrow_mut().reverse();
+                Ok(MskValue::Nil)
+            }
+            other => Err(format!("reverse_mut() expects a list but got {} ({}).", other, other.type_name()).into()),
+        }
+    }
+}
+
+/// `get(list, i)` / `get(list, i, default)`：返回 `list[i]`，下标越界时返回 `default`（省略时为 nil）而不是报错。
+/// 与 `slice` 一样，负数下标从末尾开始计数。
+#[derive(Default)]
+pub struct GetNative;
+impl Callable for GetNative {
+    fn arity(&self) -> usize { 2 }
+    fn max_arity(&self) -> Option<usize> { Some(3) }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match (&args[0], whole_number(&args[1])) {
+            (MskValue::List(items), Some(i)) => {
+                let items = items.borrow();
+                let i = if i < 0 { i + items.len() as i64 } else { i };
+                let item = usize::try_from(i).ok().and_then(|i| items.get(i));
+                Ok(item.or(args.get(2)).cloned().unwrap_or(MskValue::Nil))
+            }
+            _ => Err("get() expects a list and a whole-number index.".to_string().into()),
+        }
+    }
+}
+
+/// `enumerate(list)`：返回由 `[下标, 元素]` 组成的新列表，下标从 0 开始。
+#[derive(Default)]
+pub struct EnumerateNative;
+impl Callable for EnumerateNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match &args[0] {
+            MskValue::List(items) => {
+                let pairs = items
+                    .borrow()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| MskValue::List(Rc::new(RefCell::new(vec![MskValue::Int(i as i64), item.clone()]))))
+                    .collect();
+                Ok(MskValue::List(Rc::new(RefCell::new(pairs))))
+            }
+            other => Err(format!("enumerate() expects a list but got {} ({}).", other, other.type_name()).into()),
+        }
+    }
+}
+
+/// `flatten(list)`：把嵌套的列表展开一层，非列表元素原样保留。
+#[derive(Default)]
+pub struct FlattenNative;
+impl Callable for FlattenNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match &args[0] {
+            MskValue::List(items) => {
+                let mut flat = Vec::new();
+                for item in items.borrow().iter() {
+                    push_flattened(&mut flat, item.clone());
+                }
+                Ok(MskValue::List(Rc::new(RefCell::new(flat))))
+            }
+            _ => Err("flatten() expects a list.".to_string().into()),
+        }
+    }
+}
+
+/// `flat_map(list, f)`：对每个元素调用 `f`，再把结果展开一层拼接成一个列表。
+#[derive(Default)]
+pub struct FlatMapNative;
+impl Callable for FlatMapNative {
+    fn arity(&self) -> usize { 2 }
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match (&args[0], &args[1]) {
+            (MskValue::List(items), MskValue::Callable(f)) => {
+                // 先复制一份元素，回调函数可能会修改原列表
+                let items = items.borrow().clone();
+                let mut flat = Vec::new();
+                for item in items {
+                    push_flattened(&mut flat, interpreter.call_back(f, vec![item])?);
+                }
+                Ok(MskValue::List(Rc::new(RefCell::new(flat))))
+            }
+            _ => Err("flat_map() expects a list and a function.".to_string().into()),
+        }
+    }
+}
+
+/// `bench(f, n)`：调用无参函数 `f` 共 `n` 次，返回平均每次调用耗费的毫秒数。
+/// 计时使用解释器的时钟，`f` 出错时立即返回该错误。
+#[derive(Default)]
+pub struct BenchNative;
+impl Callable for BenchNative {
+    fn arity(&self) -> usize { 2 }
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let f = match &args[0] {
+            MskValue::Callable(f) if f.min_arity() == 0 => f.clone(),
+            _ => return Err("bench() expects a function that takes no arguments.".to_string().into()),
+        };
+        let iterations = match whole_number(&args[1]) {
+            Some(n) if n > 0 => n,
+            _ => return Err("bench() expects a positive integer iteration count.".to_string().into()),
+        };
+        let start = interpreter.now();
+        for _ in 0..iterations {
+            f.call(interpreter, Vec::new())?;
+        }
+        let elapsed = interpreter.now().saturating_sub(start);
+        Ok(MskValue::Float(elapsed.as_secs_f64() * 1000.0 / iterations as f64))
+    }
+}
+
+/// `range(end)` / `range(start, end)`：返回从 `start`（默认 0）开始、步长为 1、不包含 `end` 的列表。
+/// 两端都是整数时元素为整数，否则为浮点数；`start >= end` 时返回空列表。
+#[derive(Default)]
+pub struct RangeNative;
+impl Callable for RangeNative {
+    fn arity(&self) -> usize { 1 }
+    fn max_arity(&self) -> Option<usize> { Some(2) }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let (start, end) = match args.as_slice() {
+            [end] => (&MskValue::Int(0), end),
+            [start, end, ..] => (start, end),
+            [] => return Err("range() expects one or two numbers.".to_string().into()),
+        };
+        let items: Vec<MskValue> = match (start, end) {
+            (MskValue::Int(start), MskValue::Int(end)) => (*start..*end).map(MskValue::Int).collect(),
+            _ => match (start.as_f64(), end.as_f64()) {
+                (Some(start), Some(end)) if start.is_finite() && end.is_finite() => {
+                    let count = (end - start).ceil().max(0.0) as usize;
+                    (0..count).map(|i| MskValue::Float(start + i as f64)).collect()
+                }
+                (Some(_), Some(_)) => return Err("range() expects finite numbers.".to_string().into()),
+                _ => return Err("range() expects one or two numbers.".to_string().into()),
+            },
+        };
+        Ok(MskValue::List(Rc::new(RefCell::new(items))))
+    }
+}
+
+/// 取出 `take`/`drop` 的参数：一个列表和一个非负整数个数。
+fn list_and_count(name: &str, args: &[MskValue]) -> Result<(Vec<MskValue>, usize), RuntimeError> {
+    match (&args[0], whole_number(&args[1])) {
+        (MskValue::List(items), Some(n)) if n >= 0 => Ok((items.borrow().clone(), n as usize)),
+        _ => Err(format!("{}() expects a list and a non-negative whole number.", name).into()),
+    }
+}
+
+/// `take(list, n)`：返回由前 `n` 个元素组成的新列表，`n` 超过长度时返回全部元素。
+#[derive(Default)]
+pub struct TakeNative;
+impl Callable for TakeNative {
+    fn arity(&self) -> usize { 2 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let (mut items, n) = list_and_count("take", &args)?;
+        items.truncate(n);
+        Ok(MskValue::List(Rc::new(RefCell::new(items))))
+    }
+}
+
+/// `drop(list, n)`：返回去掉前 `n` 个元素后的新列表，`n` 超过长度时返回空列表。
+#[derive(Default)]
+pub struct DropNative;
+impl Callable for DropNative {
+    fn arity(&self) -> usize { 2 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let (mut items, n) = list_and_count("drop", &args)?;
+        items.drain(..n.min(items.len()));
+        Ok(MskValue::List(Rc::new(RefCell::new(items))))
+    }
+}
+
+/// `to_array(x)`：把字符串拆成单字符字符串组成的列表，列表原样返回。
+#[derive(Default)]
+pub struct ToArrayNative;
+impl Callable for ToArrayNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match &args[0] {
+            MskValue::String(s) => {
+                let chars = s.chars().map(|c| MskValue::String(c.to_string())).collect();
+                Ok(MskValue::List(Rc::new(RefCell::new(chars))))
+            }
+            MskValue::List(_) => Ok(args[0].clone()),
+            _ => Err("to_array() expects a string or list.".to_string().into()),
+        }
+    }
+}
+
+/// 列表的元素逐个追加到 `out`，其他值作为单个元素追加。
+fn push_flattened(out: &mut Vec<MskValue>, value: MskValue) {
+    match value {
+        MskValue::List(inner) => out.extend(inner.borrow().iter().cloned()),
+        other => out.push(other),
+    }
+}
+
+/// `ord(s)`：返回单字符字符串的 Unicode 码点。
+#[derive(Default)]
+pub struct OrdNative;
+impl Callable for OrdNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        if let MskValue::String(s) = &args[0] {
+            let mut chars = s.chars();
+            if let (Some(c), None) = (chars.next(), chars.next()) {
+                return Ok(MskValue::Int(c as u32 as i64));
+            }
+        }
+        Err("ord() expects a string of exactly one character.".to_string().into())
+    }
+}
+
+/// `chr(n)`：返回码点 `n` 对应的单字符字符串。
+#[derive(Default)]
+pub struct ChrNative;
+impl Callable for ChrNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        if let Some(n) = args[0].as_f64() {
+            if n.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(&n) {
+                if let Some(c) = char::from_u32(n as u32) {
+                    return Ok(MskValue::String(c.to_string()));
+                }
+            }
+            return Err(format!("chr() got an invalid code point {}.", n).into());
+        }
+        Err("chr() expects a number.".to_string().into())
+    }
+}
+
+/// `sb_new()`：创建一个空的字符串缓冲区。
+/// 反复使用 `+` 拼接字符串每次都会重新分配，缓冲区则在原地追加。
+#[derive(Default)]
+pub struct SbNewNative;
+impl Callable for SbNewNative {
+    fn arity(&self) -> usize { 0 }
+    fn call(&self, _interpreter: &mut Interpreter, _args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        Ok(MskValue::StringBuilder(Rc::new(RefCell::new(String::new()))))
+    }
+}
+
+/// `sb_append(sb, x)`：把 `x` 的显示形式追加到缓冲区末尾，返回缓冲区本身以便链式调用。
+#[derive(Default)]
+pub struct SbAppendNative;
+impl Callable for SbAppendNative {
+    fn arity(&self) -> usize { 2 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match &args[0] {
+            MskValue::StringBuilder(sb) => {
+                sb.borrow_mut().push_str(&args[1].to_string());
+                Ok(args[0].clone())
+            }
+            _ => Err("sb_append() expects a string builder as its first argument.".to_string().into()),
+        }
     }
 }
+
+/// `sb_build(sb)`：返回缓冲区当前内容组成的字符串。
+#[derive(Default)]
+pub struct SbBuildNative;
+impl Callable for SbBuildNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match &args[0] {
+            MskValue::StringBuilder(sb) => Ok(MskValue::String(sb.borrow().clone())),
+            _ => Err("sb_build() expects a string builder.".to_string().into()),
+        }
+    }
+}
+
+/// `env(name)` / `env(name, default)`：读取环境变量，未设置时返回 nil 或给定的默认值。
+#[derive(Default)]
+pub struct EnvNative;
+impl Callable for EnvNative {
+    fn arity(&self) -> usize { 1 }
+    fn max_arity(&self) -> Option<usize> { Some(2) }
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let name = match &args[0] {
+            MskValue::String(name) => name,
+            _ => return Err("env() expects a variable name string.".to_string().into()),
+        };
+        match interpreter.env_var(name) {
+            Some(value) => Ok(MskValue::String(value)),
+            None => Ok(args.get(1).cloned().unwrap_or(MskValue::Nil)),
+        }
+    }
+}
+
+/// `argv()`：以字符串列表的形式返回传给脚本的命令行参数（文件名之后的参数）。
+#[derive(Default)]
+pub struct ArgvNative;
+impl Callable for ArgvNative {
+    fn arity(&self) -> usize { 0 }
+    fn call(&self, interpreter: &mut Interpreter, _args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let args = interpreter.args().iter().map(|arg| MskValue::String(arg.clone())).collect();
+        Ok(MskValue::List(Rc::new(RefCell::new(args))))
+    }
+}
+
+/// `sleep(ms)`：暂停执行给定的毫秒数，返回 nil。实际的等待交给解释器的 sleeper 完成。
+#[derive(Default)]
+pub struct SleepNative;
+impl Callable for SleepNative {
+    fn arity(&self) -> usize { 1 }
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match args[0].as_f64() {
+            Some(ms) if ms >= 0.0 && ms.is_finite() => match Duration::try_from_secs_f64(ms / 1000.0) {
+                Ok(duration) => {
+                    interpreter.sleep(duration);
+                    Ok(MskValue::Nil)
+                }
+                Err(_) => Err("sleep() duration is too large.".to_string().into()),
+            },
+            Some(ms) => Err(format!("sleep() expects a non-negative duration but got {}.", ms).into()),
+            None => Err("sleep() expects a number of milliseconds.".to_string().into()),
+        }
+    }
+}
+
+/// 将 Unix 时间戳（秒）格式化为 `YYYY-MM-DDTHH:MM:SSZ`。
+/// 日期换算使用 Howard Hinnant 的 civil_from_days 算法，不依赖外部时间库。
+fn format_iso8601(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    let (hour, minute, second) = (rem / 3600, rem % 3600 / 60, rem % 60);
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, hour, minute, second)
+}
 /// 宏：将原生函数注册到环境中
 ///
 /// # 参数
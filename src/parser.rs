@@ -3,10 +3,11 @@
 
 use log::error;
 use crate::ast::Stmt::Expression;
-use crate::ast::{Expr, Stmt};
+use crate::ast::{Expr, MatchArm, Pattern, Stmt};
+use crate::token::Literal;
 use crate::token::{Token, TokenType};
-use std::cell::Cell;
-use std::process::exit;
+use std::cell::{Cell, RefCell};
+use crate::diagnostic::{Diagnostic, Stage};
 use std::rc::Rc;
 use log::info;
 
@@ -18,6 +19,16 @@ pub struct Parser {
     current: usize,
     /// 记录在解析过程中是否遇到了错误。
     had_error: Cell<bool>,
+    /// 出错后进入恐慌模式：不再记录新的错误，各层解析尽快返回，
+    /// 直到 `synchronize` 跳到下一条语句的开头。
+    panic_mode: Cell<bool>,
+    /// 解析过程中报告的错误。每条语句最多记录一个错误，避免由它引发的连锁错误。
+    diagnostics: RefCell<Vec<Diagnostic>>,
+    /// 是否允许调用后紧跟尾随块 `f(x) { ... }`。
+    /// 解析 `match` 的被匹配表达式时关闭，否则 `match f() { ... }` 的分支会被当成尾随块。
+    allow_trailing_block: bool,
+    /// 自动分号插入（ASI）模式：语句末尾的 `;` 可以用换行代替。默认关闭。
+    asi: bool,
 }
 
 impl Parser {
@@ -27,20 +38,41 @@ impl Parser {
             tokens,
             current: 0,
             had_error: Cell::new(false),
+            panic_mode: Cell::new(false),
+            diagnostics: RefCell::new(Vec::new()),
+            allow_trailing_block: true,
+            asi: false,
+        }
+    }
+
+    /// 开启或关闭自动分号插入模式，开启后换行也可以结束一条语句，例如
+    /// `print 1` 换行 `print 2`。
+    pub fn set_asi(&mut self, enabled: bool) {
+        self.asi = enabled;
+    }
+
+    /// 取出解析过程中收集到的错误。
+    pub fn take_diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.take()
+    }
+
+    /// 将收集到的错误输出到 stderr。
+    fn print_diagnostics(&self) {
+        for diagnostic in self.diagnostics.borrow().iter() {
+            eprintln!("{}", diagnostic);
         }
     }
 
     /// 开始解析 Token 序列，尝试构建一个 AST 表达式。
     /// 如果解析成功，返回 `Some(Expr)`；如果遇到错误，则返回 `None`。
     /// 同时返回一个布尔值，表示在解析过程中是否发生了错误。
+    /// 遇到的错误会输出到 stderr。
     pub fn parse(&mut self) -> (Option<Vec<Stmt>>, bool) {
         if self.peek().token_type == TokenType::Eof {
             return (None, self.had_error.get());
         }
-        let mut stmts: Vec<Stmt> = Vec::new();
-        while !self.is_at_end() {
-            stmts.push(self.statement());
-        }
+        let stmts = self.parse_statements();
+        self.print_diagnostics();
 
         // if !self.is_at_end() {
         //     // self.error(self.peek(), "Expect end of expression.");
@@ -51,7 +83,64 @@ impl Parser {
             (Some(stmts), false)
         }
     }
+
+    /// 解析整个程序，出错时返回收集到的错误，不向 stderr 输出任何内容。
+    pub fn parse_with_diagnostics(&mut self) -> Result<Vec<Stmt>, Vec<Diagnostic>> {
+        let stmts = self.parse_statements();
+        if self.had_error.get() {
+            Err(self.take_diagnostics())
+        } else {
+            Ok(stmts)
+        }
+    }
+
+    fn parse_statements(&mut self) -> Vec<Stmt> {
+        let mut stmts: Vec<Stmt> = Vec::new();
+        while !self.is_at_end() {
+            stmts.push(self.statement());
+        }
+        stmts
+    }
+    /// 解析一条语句。语句中出现错误时跳到下一条语句的开头继续解析，
+    /// 这样一次运行可以报告多个互不相关的错误。
     fn statement(&mut self) -> Stmt {
+        let start = self.current;
+        let stmt = self.statement_kind();
+        if self.panic_mode.get() {
+            // 出错的语句一个 Token 都没有消费时先跳过一个，保证解析能够前进
+            if self.current == start {
+                self.advance();
+            }
+            self.synchronize();
+        }
+        stmt
+    }
+    /// 跳过 Token 直到语句边界：刚消费了 `;`，或下一个 Token 是语句开头的关键字。
+    /// ASI 模式下换行也是语句边界。
+    fn synchronize(&mut self) {
+        self.panic_mode.set(false);
+        while !self.is_at_end() {
+            if self.previous().token_type == TokenType::Semicolon {
+                return;
+            }
+            if self.asi && self.peek().line > self.previous().line {
+                return;
+            }
+            match self.peek().token_type {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return => return,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+    fn statement_kind(&mut self) -> Stmt {
         if self.match_token(&[TokenType::While]) {
             return self.while_statement();
         }
@@ -78,78 +167,207 @@ impl Parser {
         if self.match_token(&[TokenType::Continue]) {
             return self.continue_statement();
         }
-        if self.match_token(&[TokenType::Fun]){
-            return self.function_statement();
+        if self.match_token(&[TokenType::Class]) {
+            return self.class_declaration();
+        }
+        // `fun` 后面紧跟名字才是函数声明，`fun (...) {...}` 是以匿名函数开头的表达式语句
+        if self.check(&TokenType::Fun) && self.check_next(1, &TokenType::Identifier) {
+            self.advance();
+            return self.function_declaration();
         }
         if self.match_token(&[TokenType::Return]) {
             return self.return_statement();
         }
+        if self.match_token(&[TokenType::Match]) {
+            return self.match_statement();
+        }
+        if self.match_token(&[TokenType::Using]) {
+            return self.using_statement();
+        }
         self.expression_statement()
     }
+    /// match_statement -> "match" expression "{" ( pattern "=>" statement ","? )* "}" ;
+    fn match_statement(&mut self) -> Stmt {
+        let name = self.previous().clone();
+        self.allow_trailing_block = false;
+        let subject = self.expression();
+        self.allow_trailing_block = true;
+        self.consume(TokenType::LeftBrace, "Expect '{' after match subject.");
+
+        let mut arms = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() && !self.panic_mode.get() {
+            let pattern = self.pattern();
+            self.consume(TokenType::FatArrow, "Expect '=>' after pattern.");
+            let body = self.statement();
+            arms.push(MatchArm { pattern, body });
+            self.match_token(&[TokenType::Comma]);
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after match arms.");
+        Stmt::Match { name, subject, arms }
+    }
+    /// pattern -> literal | "-" NUMBER | IDENTIFIER | "[" ( pattern ( "," pattern )* ","? )? "]" ;
+    fn pattern(&mut self) -> Pattern {
+        // 列表模式，与列表字面量一样允许末尾多一个逗号
+        if self.match_token(&[TokenType::LeftBracket]) {
+            let mut elements = Vec::new();
+            while !self.check(&TokenType::RightBracket) && !self.panic_mode.get() {
+                elements.push(self.pattern());
+                if !self.match_token(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+            self.consume(TokenType::RightBracket, "Expect ']' after list pattern.");
+            return Pattern::List(elements);
+        }
+        if self.match_token(&[
+            TokenType::False,
+            TokenType::True,
+            TokenType::Nil,
+            TokenType::String,
+            TokenType::Number,
+        ]) {
+            return Pattern::Literal(self.previous().clone());
+        }
+        // 负数字面量模式，例如 `-1`
+        if self.check(&TokenType::Minus) {
+            let minus = self.advance().clone();
+            let number = self.consume(TokenType::Number, "Expect number after '-' in pattern.").clone();
+            let negated = match number.literal {
+                Some(Literal::Number(n)) => Literal::Number(-n),
+                Some(Literal::Integer(n)) => Literal::Integer(-n),
+                _ => return Pattern::Wildcard,
+            };
+            return Pattern::Literal(Token::new(
+                TokenType::Number,
+                format!("-{}", number.lexeme),
+                Some(negated),
+                number.line,
+                minus.column,
+            ));
+        }
+        if self.match_token(&[TokenType::Identifier]) {
+            let name = self.previous().clone();
+            if name.lexeme == "_" {
+                return Pattern::Wildcard;
+            }
+            return Pattern::Binding(name);
+        }
+        self.error(self.peek(), "Expect pattern.");
+        Pattern::Wildcard
+    }
     fn return_statement(&mut self) -> Stmt {
         let name = self.previous().clone();
         let mut value = None;
-        if !self.check(&TokenType::Semicolon) {
-            value = Some(self.expression());
+        if !self.at_statement_end() {
+            let first = self.expression();
+            // `return a, b;` 返回多个值，打包成列表 `[a, b]`；单个值原样返回
+            if self.check(&TokenType::Comma) {
+                let mut elements = vec![first];
+                while self.match_token(&[TokenType::Comma]) {
+                    elements.push(self.expression());
+                }
+                value = Some(Expr::ListLiteral { bracket: name.clone(), elements });
+            } else {
+                value = Some(first);
+            }
         }
-        self.consume(TokenType::Semicolon, "Expect ';' after return value.");
+        self.consume_terminator("Expect ';' after return value.");
         Stmt::Return { name, value }
     }
-    fn function_statement(&mut self) -> Stmt {
-        if self.match_token(&[TokenType::Identifier]) {
-            let name = self.previous().clone();
-            self.consume(TokenType::LeftParen, "Expect '(' after function name.");
+    /// 解析函数声明
+    /// function_declaration -> "fun" IDENTIFIER "(" ( IDENTIFIER ( "," IDENTIFIER )* )? ")" block ;
+    fn function_declaration(&mut self) -> Stmt {
+        let name = self.consume(TokenType::Identifier, "Expect function name.").clone();
+        self.consume(TokenType::LeftParen, "Expect '(' after function name.");
+        let (params, rest) = self.parameters();
+        self.consume(TokenType::LeftBrace, "Expect '{' before function body.");
+        let body = self.block_statement();
+        Stmt::Function {
+            name,
+            params,
+            rest,
+            body: Rc::new(body),
+        }
+    }
+    /// classDecl -> "class" IDENTIFIER ( "<" IDENTIFIER )? "{" function* "}" ;
+    /// 方法的写法与函数声明相同，只是省略了 `fun` 关键字。
+    fn class_declaration(&mut self) -> Stmt {
+        let name = self.consume(TokenType::Identifier, "Expect class name.").clone();
+        let mut superclass = None;
+        if self.match_token(&[TokenType::Less]) {
+            let name = self.consume(TokenType::Identifier, "Expect superclass name.").clone();
+            superclass = Some(Expr::Variable { name, depth: Cell::new(None) });
+        }
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.");
 
-            let mut parameters = Vec::new();
-            while self.match_token(&[TokenType::Identifier]) {
-                let parameter = self.previous().clone();
-                parameters.push(parameter);
-                if self.match_token(&[TokenType::RightParen]) {
-                    break;
-                }
-                self.consume(TokenType::Comma, "Expect ',' after parameter.");
-                if self.check(&TokenType::Identifier) {
-                    continue;
+        let mut methods = Vec::new();
+        let mut statics = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() && !self.panic_mode.get() {
+            // `static` 不是关键字，只有后面紧跟成员名时才作为修饰符，因此名为 `static` 的方法仍然可用
+            if self.peek().lexeme == "static" && self.check_next(1, &TokenType::Identifier) {
+                self.advance();
+                if self.check_next(1, &TokenType::LeftParen) {
+                    statics.push(self.function_declaration());
                 } else {
-                    self.error(self.peek(), "Expect parameter name.");
-                    break;
+                    statics.push(self.var_declaration());
                 }
+            } else {
+                methods.push(self.function_declaration());
             }
-            if parameters.is_empty() {
-                if !self.match_token(&[TokenType::RightParen]) {
-                    self.error(self.peek(), "Expect ')' after left brace.");
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.");
+        Stmt::Class { name, superclass, methods, statics }
+    }
+    /// 解析参数列表，调用前已经消费了 `(`，返回时已经消费了 `)`。
+    /// parameters -> ( IDENTIFIER ( "," IDENTIFIER )* ( "," "..." IDENTIFIER )? | "..." IDENTIFIER )? ;
+    /// 返回固定参数和可选的剩余参数。
+    fn parameters(&mut self) -> (Vec<Token>, Option<Token>) {
+        let mut params = Vec::new();
+        let mut rest = None;
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if self.match_token(&[TokenType::Ellipsis]) {
+                    rest = Some(self.consume(TokenType::Identifier, "Expect parameter name after '...'.").clone());
+                    if self.check(&TokenType::Comma) {
+                        self.error(self.peek(), "Rest parameter must be the last parameter.");
+                    }
+                    break;
                 }
-            }
-            if self.match_token(&[TokenType::LeftBrace]) {
-                // 处理块语句
-                let body = self.block_statement();
-                return Stmt::Function {
-                    name,
-                    params: parameters,
-                    body: Rc::new(body),
+                params.push(self.consume(TokenType::Identifier, "Expect parameter name.").clone());
+                if !self.match_token(&[TokenType::Comma]) || self.panic_mode.get() {
+                    break;
                 }
-            } else {
-                self.error(self.peek(), "Expect '{' after function parameters.");
             }
-
-        }else{
-            self.error(self.peek(), "Expect Identifier after function parameters.");
-        }
-        Stmt::Function {
-            name: Token {
-                token_type: TokenType::Identifier,
-                lexeme: "anonymous".to_string(),
-                literal: None,
-                line: self.peek().line,
-            },
-            params: Vec::new(),
-            body: Rc::new(Stmt::Block { statements: vec![] }),
         }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.");
+        (params, rest)
     }
     fn for_statement(&mut self) -> Stmt {
         let name = self.previous().clone();
         self.consume(TokenType::LeftParen, "Expect '(' after 'for'.");
 
+        // `for (var x in expr)` 或 `for (var i, x in expr)`：遍历列表或字符串
+        let two_names = self.check_next(2, &TokenType::Comma)
+            && self.check_next(3, &TokenType::Identifier)
+            && self.check_next(4, &TokenType::In);
+        if self.check(&TokenType::Var)
+            && self.check_next(1, &TokenType::Identifier)
+            && (self.check_next(2, &TokenType::In) || two_names) {
+            self.advance(); // 'var'
+            let mut var_name = self.advance().clone();
+            let mut index_name = None;
+            if two_names {
+                self.advance(); // ','
+                index_name = Some(var_name);
+                var_name = self.advance().clone();
+            }
+            self.advance(); // 'in'
+            let iterable = self.expression();
+            self.consume(TokenType::RightParen, "Expect ')' after for-in iterable.");
+            let body = Box::new(self.statement());
+            return Stmt::ForEach { name, index_name, var_name, iterable, body };
+        }
+
         let initializer = if self.match_token(&[TokenType::Var]) {
             Some(Box::new(self.var_declaration()))
         } else if self.match_token(&[TokenType::Semicolon]) {
@@ -181,28 +399,56 @@ impl Parser {
             body,
         }
     }
+    /// break_statement -> "break" ( IDENTIFIER expression? )? ";" ;
     fn break_statement(&mut self) -> Stmt {
         let name = self.previous().clone();
-        self.consume(TokenType::Semicolon, "Expect ';' after break statement.");
-        Stmt::Break { name }
+        let mut label = None;
+        let mut value = None;
+        if self.match_token(&[TokenType::Identifier]) {
+            label = Some(self.previous().clone());
+            if !self.at_statement_end() {
+                value = Some(self.expression());
+            }
+        }
+        self.consume_terminator("Expect ';' after break statement.");
+        Stmt::Break { name, label, value }
     }
     fn continue_statement(&mut self) -> Stmt {
         let name = self.previous().clone();
-        self.consume(TokenType::Semicolon, "Expect ';' after continue statement.");
+        self.consume_terminator("Expect ';' after continue statement.");
         Stmt::Continue { name }
     }
     fn while_statement(&mut self) -> Stmt {
         let name = self.previous().clone();
         self.consume(TokenType::LeftParen, "Expect '(' after 'While'.");
+        // `while (var line = readline())`：条件位置允许声明一个变量
+        let mut binding = None;
+        if self.match_token(&[TokenType::Var]) {
+            binding = Some(self.consume(TokenType::Identifier, "Expect variable name.").clone());
+            self.consume(TokenType::Equal, "Expect '=' after variable name in while condition.");
+        }
         let condition = self.expression();
         self.consume(TokenType::RightParen, "Expect ')' after 'the condition of While statement'.");
         let body = Box::new(self.statement());
         Stmt::While {
             name,
+            binding,
             condition,
             body
         }
     }
+    /// using_statement -> "using" "(" "var" IDENTIFIER "=" expression ")" statement ;
+    fn using_statement(&mut self) -> Stmt {
+        let name = self.previous().clone();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'using'.");
+        self.consume(TokenType::Var, "Expect 'var' in using statement.");
+        let var_name = self.consume(TokenType::Identifier, "Expect variable name.").clone();
+        self.consume(TokenType::Equal, "Expect '=' after variable name in using statement.");
+        let initializer = self.expression();
+        self.consume(TokenType::RightParen, "Expect ')' after using resource.");
+        let body = Box::new(self.statement());
+        Stmt::Using { name, var_name, initializer, body }
+    }
     fn if_statement(&mut self) -> Stmt {
         let name = self.previous().clone();
         self.consume(TokenType::LeftParen, "Expect '(' after 'if'.");
@@ -212,6 +458,9 @@ impl Parser {
         let mut else_branch = None;
         if self.match_token(&[TokenType::Else]) {
             else_branch = Some(Box::new(self.statement()));
+        } else if self.match_token(&[TokenType::Elif]) {
+            // `elif (...)` 等价于 `else if (...)`
+            else_branch = Some(Box::new(self.if_statement()));
         }
         Stmt::If {
             name,
@@ -230,13 +479,13 @@ impl Parser {
             initializer = Some(self.expression());
         }
 
-        self.consume(TokenType::Semicolon, "Expect ';' after variable declaration.");
+        self.consume_terminator("Expect ';' after variable declaration.");
         Stmt::Var { name, initializer }
     }
 
     fn print_statement(&mut self) -> Stmt {
         let value = self.expression();
-        self.consume(TokenType::Semicolon, "Expect ';' after value.");
+        self.consume_terminator("Expect ';' after value.");
         Stmt::Print {
             expression: value,
         }
@@ -248,6 +497,9 @@ impl Parser {
                 self.error(self.peek(), "Expect '}' after block.");
                 break;
             }
+            if self.panic_mode.get() {
+                break;
+            }
             stmts.push(self.statement());
         }
         Stmt::Block {
@@ -256,7 +508,14 @@ impl Parser {
     }
     fn expression_statement(&mut self) -> Stmt {
         let expr = self.expression();
-        self.consume(TokenType::Semicolon, "Expect ';' after expression.");
+        // 以尾随块结尾的调用语句（`repeat(3) { ... }`）可以省略分号
+        let ends_with_trailing_block = matches!(&expr, Expr::Call { .. })
+            && self.previous().token_type == TokenType::RightBrace;
+        if ends_with_trailing_block {
+            self.match_token(&[TokenType::Semicolon]);
+        } else {
+            self.consume_terminator("Expect ';' after expression.");
+        }
         Expression { expression: expr }
     }
     fn increment_statement(&mut self) -> Stmt {
@@ -271,6 +530,7 @@ impl Parser {
         if !self.is_at_end() {
             // self.error(self.peek(), "Expect end of expression.");
         }
+        self.print_diagnostics();
         if self.had_error.get() {
             (None, true)
         } else {
@@ -283,26 +543,119 @@ impl Parser {
         self.assignment()
     }
 
+    /// 解析赋值表达式。赋值是右结合的，`a = b = c = 0` 会被解析为 `a = (b = (c = 0))`：
+    /// 右侧只求值一次，然后从右到左依次赋给每个目标，每个目标都必须是可赋值的变量。
+    /// assignment -> IDENTIFIER ( "=" | "min" "=" | "max" "=" ) assignment
+    ///             | call "[" expression "]" ( "=" | "min" "=" | "max" "=" ) assignment
+    ///             | call "." IDENTIFIER "=" assignment
+    ///             | "(" IDENTIFIER ( "," IDENTIFIER )* ")" "=" assignment
+    ///             | ternary ;
     fn assignment(&mut self) -> Expr {
-        let expr = self.logic();
+        let expr = self.ternary();
         if self.match_token(&[TokenType::Equal]) {
             let equals = self.previous().clone();
             let value = self.assignment(); // 右结合性：递归调用自己
 
-            if let Expr::Variable { name } = expr {
+            if let Expr::Variable { name, .. } = expr {
                 return Expr::Assign {
                     name,
                     value: Box::new(value),
+                    depth: Cell::new(None),
+                };
+            }
+            if let Expr::Index { object, bracket, index } = expr {
+                return Expr::SetIndex {
+                    object,
+                    bracket,
+                    index,
+                    value: Box::new(value),
                 };
             }
+            if let Expr::Get { object, name } = expr {
+                return Expr::Set {
+                    object,
+                    name,
+                    value: Box::new(value),
+                };
+            }
+            // 并行赋值：左侧必须是全部由变量组成的括号列表
+            if let Expr::Tuple { elements, .. } = &expr {
+                let names: Vec<Token> = elements.iter()
+                    .filter_map(|e| match e {
+                        Expr::Variable { name, .. } => Some(name.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                if names.len() == elements.len() {
+                    return Expr::MultiAssign {
+                        depths: vec![Cell::new(None); names.len()],
+                        names,
+                        equals,
+                        value: Box::new(value),
+                    };
+                }
+            }
             self.error(&equals, "Invalid assignment target.");
         }
+        // `x min= v` / `x max= v`：min、max 只在表达式后紧跟 `=` 时才是运算符，
+        // 其他位置仍然是普通标识符，例如 `var min = 1;`。
+        // ASI 模式下换行会结束语句，所以 `min=` 必须和左侧表达式在同一行
+        if self.check(&TokenType::Identifier)
+            && matches!(self.peek().lexeme.as_str(), "min" | "max")
+            && self.check_next(1, &TokenType::Equal)
+            && !(self.asi && self.tokens[self.current + 1].line > self.previous().line)
+        {
+            let operator = self.advance().clone();
+            self.advance(); // 消耗 '='
+            let value = self.assignment();
+            if matches!(expr, Expr::Variable { .. } | Expr::Index { .. }) {
+                return Expr::MinMaxAssign {
+                    target: Box::new(expr),
+                    operator,
+                    value: Box::new(value),
+                };
+            }
+            self.error(&operator, "Invalid assignment target.");
+        }
+        expr
+    }
+    /// 条件表达式，右结合：`a ? b : c ? d : e` 解析为 `a ? b : (c ? d : e)`。
+    /// ternary -> logic_or ( "?" expression ":" ternary )? ;
+    fn ternary(&mut self) -> Expr {
+        let condition = self.logic_or();
+        if self.match_token(&[TokenType::Question]) {
+            let then_branch = self.expression();
+            self.consume(TokenType::Colon, "Expect ':' after then branch of conditional expression.");
+            let else_branch = self.ternary();
+            return Expr::Ternary {
+                condition: Box::new(condition),
+                then_branch: Box::new(then_branch),
+                else_branch: Box::new(else_branch),
+            };
+        }
+        condition
+    }
+    /// 逻辑或表达式，优先级低于 `and`。
+    /// logic_or -> logic_and ( "or" logic_and )* ;
+    fn logic_or(&mut self) -> Expr {
+        let mut expr = self.logic_and();
+        while self.match_token(&[TokenType::Or]) {
+            let operator = self.previous().clone();
+            let right = self.logic_and();
+            expr = Expr::Logical {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
         expr
     }
-    /// 逻辑表达式解析入口。
-    fn logic(&mut self) -> Expr {
+
+    /// 逻辑与表达式。
+    /// logic_and -> equality ( "and" equality )* ;
+    fn logic_and(&mut self) -> Expr {
         let mut expr = self.equality();
-        while self.match_token(&[TokenType::Or,TokenType::And]) {
+        while self.match_token(&[TokenType::And]) {
             let operator = self.previous().clone();
             let right = self.equality();
             expr = Expr::Logical {
@@ -314,9 +667,10 @@ impl Parser {
         expr
     }
 
+    /// equality -> comparison ( ( "!=" | "==" ) comparison )* ;
     fn equality(&mut self) -> Expr {
         let mut expr = self.comparison();
-        if self.match_token(&[
+        while self.match_token(&[
             TokenType::EqualEqual,
             TokenType::BangEqual,
         ]) {
@@ -331,6 +685,7 @@ impl Parser {
         expr
     }
 
+    /// comparison -> term ( ( ">" | ">=" | "<" | "<=" | "in" ) term )* ;
     fn comparison(&mut self) -> Expr {
         let mut expr = self.term();
         while self.match_token(&[
@@ -338,6 +693,7 @@ impl Parser {
             TokenType::GreaterEqual,
             TokenType::Less,
             TokenType::LessEqual,
+            TokenType::In,
         ]) {
             let operator = self.previous().clone();
             let right = self.term();
@@ -367,7 +723,7 @@ impl Parser {
     fn factor(&mut self) -> Expr {
         let mut expr = self.unary();
 
-        while self.match_token(&[TokenType::Slash, TokenType::Star]) {
+        while self.match_token(&[TokenType::Slash, TokenType::Star, TokenType::Percent]) {
             let operator = self.previous().clone();
             let right = self.unary();
             expr = Expr::Binary {
@@ -394,12 +750,42 @@ impl Parser {
         // 如果不是一元运算符，则继续解析主表达式。
         self.call()
     }
+    /// call -> primary ( "(" arguments? ")" | "[" expression "]" | "." IDENTIFIER )* ;
+    /// arguments -> "..."? expression ( "," "..."? expression )* ;
     fn call(&mut self) -> Expr {
         let mut expr = self.primary();
-        while self.match_token(&[TokenType::LeftParen]) {
+        loop {
+            if self.match_token(&[TokenType::Dot]) {
+                let name = self.consume(TokenType::Identifier, "Expect property name after '.'.").clone();
+                expr = Expr::Get {
+                    object: Box::new(expr),
+                    name,
+                };
+                continue;
+            }
+            if self.match_token(&[TokenType::LeftBracket]) {
+                let bracket = self.previous().clone();
+                let index = self.expression();
+                self.consume(TokenType::RightBracket, "Expect ']' after index.");
+                expr = Expr::Index {
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                };
+                continue;
+            }
+            if !self.match_token(&[TokenType::LeftParen]) {
+                break;
+            }
             let mut arguments = Vec::new();
-            while !self.check(&TokenType::RightParen) {
-                arguments.push(self.expression());
+            while !self.check(&TokenType::RightParen) && !self.panic_mode.get() {
+                if self.match_token(&[TokenType::Ellipsis]) {
+                    let ellipsis = self.previous().clone();
+                    let expression = Box::new(self.expression());
+                    arguments.push(Expr::Spread { ellipsis, expression });
+                } else {
+                    arguments.push(self.expression());
+                }
                 if self.check(&TokenType::RightParen){
                     break;
                 }
@@ -407,6 +793,17 @@ impl Parser {
             }
             if self.match_token(&[TokenType::RightParen]) {
                 let paren = self.previous().clone();
+                // 尾随块：`repeat(3) { ... }` 中的块会被包装成无参函数，作为最后一个实参传入
+                if self.allow_trailing_block && self.match_token(&[TokenType::LeftBrace]) {
+                    let keyword = self.previous().clone();
+                    let body = self.block_statement();
+                    arguments.push(Expr::Function {
+                        keyword,
+                        params: Vec::new(),
+                        rest: None,
+                        body: Rc::new(body),
+                    });
+                }
                 expr =  Expr::Call {
                     callee: Box::new(expr),
                     paren,
@@ -414,13 +811,17 @@ impl Parser {
                 };
             }else{
                 self.error(self.peek(), "Expect ')' after arguments.");
-                unreachable!()
+                break;
             }
         }
         expr
     }
     /// 解析一个主表达式。
-    /// primary -> NUMBER | STRING | "true" | "false" | "nil" | "(" expression ")" | IDENTIFIER
+    /// primary -> NUMBER | STRING | "true" | "false" | "nil" | "this" | "(" expression ")" | IDENTIFIER
+    ///          | "super" "." IDENTIFIER
+    ///          | "if" "(" expression ")" expression "else" expression
+    ///          | "[" ( expression ( "," expression )* ","? )? "]"
+    ///          | "fun" "(" parameters? ")" block
     fn primary(&mut self) -> Expr {
 
         if self.match_token(&[
@@ -435,14 +836,100 @@ impl Parser {
             };
         }
 
+        if self.match_token(&[TokenType::This]) {
+            return Expr::This {
+                keyword: self.previous().clone(),
+                depth: Cell::new(None),
+            };
+        }
+
+        if self.match_token(&[TokenType::Super]) {
+            let keyword = self.previous().clone();
+            self.consume(TokenType::Dot, "Expect '.' after 'super'.");
+            let method = self.consume(TokenType::Identifier, "Expect superclass method name.").clone();
+            return Expr::Super {
+                keyword,
+                method,
+                depth: Cell::new(None),
+            };
+        }
+
+        // 匿名函数：`fun (x) { return x * 2; }`
+        if self.match_token(&[TokenType::Fun]) {
+            let keyword = self.previous().clone();
+            self.consume(TokenType::LeftParen, "Expect '(' after 'fun'.");
+            let (params, rest) = self.parameters();
+            self.consume(TokenType::LeftBrace, "Expect '{' before function body.");
+            let body = self.block_statement();
+            return Expr::Function {
+                keyword,
+                params,
+                rest,
+                body: Rc::new(body),
+            };
+        }
+
+        // 带标签的块表达式：IDENTIFIER ":" "{" ... "}"
+        if self.check(&TokenType::Identifier)
+            && self.check_next(1, &TokenType::Colon)
+            && self.check_next(2, &TokenType::LeftBrace) {
+            let label = self.advance().clone();
+            self.advance(); // ':'
+            self.advance(); // '{'
+            let statements = match self.block_statement() {
+                Stmt::Block { statements } => statements,
+                _ => Vec::new(),
+            };
+            return Expr::LabeledBlock { label, statements };
+        }
+
         if self.match_token(&[TokenType::Identifier]) {
             return Expr::Variable {
                 name: self.previous().clone(),
+                depth: Cell::new(None),
             };
         }
 
+        // if 表达式：出现在表达式位置的 `if`，与 if 语句不同，必须带 else 分支
+        if self.match_token(&[TokenType::If]) {
+            self.consume(TokenType::LeftParen, "Expect '(' after 'if'.");
+            let condition = self.expression();
+            self.consume(TokenType::RightParen, "Expect ')' after if condition.");
+            let then_branch = self.expression();
+            self.consume(TokenType::Else, "Expect 'else' branch in if expression.");
+            let else_branch = self.expression();
+            return Expr::IfExpr {
+                condition: Box::new(condition),
+                then_branch: Box::new(then_branch),
+                else_branch: Box::new(else_branch),
+            };
+        }
+
+        // 列表字面量，允许末尾多一个逗号：`[1, 2, 3,]`
+        if self.match_token(&[TokenType::LeftBracket]) {
+            let bracket = self.previous().clone();
+            let mut elements = Vec::new();
+            while !self.check(&TokenType::RightBracket) && !self.panic_mode.get() {
+                elements.push(self.expression());
+                if !self.match_token(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+            self.consume(TokenType::RightBracket, "Expect ']' after list elements.");
+            return Expr::ListLiteral { bracket, elements };
+        }
+
         if self.match_token(&[TokenType::LeftParen]) {
+            let paren = self.previous().clone();
             let expr = self.expression();
+            if self.check(&TokenType::Comma) {
+                let mut elements = vec![expr];
+                while self.match_token(&[TokenType::Comma]) {
+                    elements.push(self.expression());
+                }
+                self.consume(TokenType::RightParen, "Expect ')' after list.");
+                return Expr::Tuple { paren, elements };
+            }
             self.consume(TokenType::RightParen, "Expect expression.");
             return Expr::Grouping {
                 expression: Box::new(expr),
@@ -454,6 +941,28 @@ impl Parser {
         }
     }
 
+    /// 当前位置是否处于语句结尾：下一个 Token 是 `;`，或者在 ASI 模式下
+    /// 下一个 Token 位于新的一行、是 `}` 或已到达文件末尾。
+    fn at_statement_end(&self) -> bool {
+        if self.check(&TokenType::Semicolon) {
+            return true;
+        }
+        self.asi
+            && (self.is_at_end()
+                || self.check(&TokenType::RightBrace)
+                || self.peek().line > self.previous().line)
+    }
+
+    /// 消耗语句末尾的 `;`。ASI 模式下语句也可以由换行、`}` 或文件末尾结束，此时不消耗任何 Token。
+    fn consume_terminator(&mut self, message: &str) {
+        if self.match_token(&[TokenType::Semicolon]) {
+            return;
+        }
+        if !self.at_statement_end() {
+            self.error(self.peek(), message);
+        }
+    }
+
     /// 检查当前 Token 是否是预期类型之一。如果是，则消耗它并返回 true。
     fn match_token(&mut self, types: &[TokenType]) -> bool {
         for token_type in types {
@@ -483,6 +992,14 @@ impl Parser {
         &self.peek().token_type == token_type
     }
 
+    /// 检查当前位置之后第 `offset` 个 Token 的类型，但不消耗任何 Token。
+    fn check_next(&self, offset: usize, token_type: &TokenType) -> bool {
+        match self.tokens.get(self.current + offset) {
+            Some(token) => &token.token_type == token_type,
+            None => false,
+        }
+    }
+
     /// 消费当前 Token 并向前移动一个位置，返回被消费的 Token。
     fn advance(&mut self) -> &Token {
         if !self.is_at_end() {
@@ -507,16 +1024,18 @@ impl Parser {
     }
 
     /// 报告一个解析错误。
+    /// 恐慌模式下不再记录，之后的错误通常是第一个错误引发的连锁反应。
     fn error(&self ,token: &Token, message: &str) {
-        if token.token_type == TokenType::Eof {
-            eprintln!("[line {}] Error at end: {}", token.line, message);
-        } else {
-            eprintln!(
-                "[line {}] Error at '{}': {}",
-                token.line, token.lexeme, message
-            );
+        if self.panic_mode.get() {
+            return;
         }
+        let text = if token.token_type == TokenType::Eof {
+            format!("[line {}] Error at end: {}", token.line, message)
+        } else {
+            format!("[line {}] Error at '{}': {}", token.line, token.lexeme, message)
+        };
+        self.diagnostics.borrow_mut().push(Diagnostic::new(Stage::Parse, Some(token.line), text).with_column(token.column));
         self.had_error.set(true);
-        exit(65);
+        self.panic_mode.set(true);
     }
 }
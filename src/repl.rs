@@ -0,0 +1,86 @@
+//! repl.rs - 交互式解释器（REPL）。
+//! 逐行读取输入，所有行共享同一个解释器，因此前面定义的变量和函数在后面仍然可用。
+
+use std::io::{self, BufRead};
+
+use crate::ast::Stmt;
+use crate::diagnostic::Diagnostic;
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::scanner::Scanner;
+
+/// 启动 REPL，从 stdin 读取输入，直到 stdin 结束（Ctrl-D）才返回。
+pub fn run_repl() {
+    let mut interpreter = Interpreter::new();
+    run_repl_with(&mut interpreter, io::stdin().lock());
+}
+
+/// 在给定的解释器中运行 REPL，逐行读取 `input` 直到结束。
+/// 提示符和结果写入解释器的输出，错误写入解释器的错误输出，便于嵌入和测试。
+/// 每行以自动分号插入模式解析，所以行尾的 `;` 可以省略；
+/// 只包含一个裸表达式的行会以 `=> 值 : 类型` 的形式打印它的值，其他语句只执行不输出。
+/// 扫描、解析或运行时错误只会被报告，不会结束循环。
+pub fn run_repl_with(interpreter: &mut Interpreter, input: impl BufRead) {
+    // 输出要与提示符交替出现，即使 stdout 被重定向也要逐行刷新
+    interpreter.set_autoflush(true);
+    let mut lines = input.lines();
+    loop {
+        let prompt = write!(interpreter.output(), "> ").and_then(|_| interpreter.output().flush());
+        if prompt.is_err() {
+            break;
+        }
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            // EOF 或读取失败都结束 REPL
+            _ => break,
+        };
+        run_line(interpreter, &line);
+    }
+    let _ = writeln!(interpreter.output());
+}
+
+/// 执行一行输入并输出结果或错误。
+fn run_line(interpreter: &mut Interpreter, line: &str) {
+    let (tokens, diagnostics) = Scanner::new(line).scan_tokens_with_diagnostics();
+    if !diagnostics.is_empty() {
+        report(interpreter, &diagnostics);
+        return;
+    }
+
+    let mut parser = Parser::new(tokens);
+    parser.set_asi(true);
+    let stmts = match parser.parse_with_diagnostics() {
+        Ok(stmts) => stmts,
+        Err(diagnostics) => {
+            report(interpreter, &diagnostics);
+            return;
+        }
+    };
+    let mut resolver = Resolver::new();
+    resolver.set_known_globals(interpreter.global_names());
+    if let Err(diagnostics) = resolver.resolve(&stmts) {
+        report(interpreter, &diagnostics);
+        return;
+    }
+
+    let result = match stmts.as_slice() {
+        [Stmt::Expression { expression }] => interpreter.evaluate(expression).and_then(|value| {
+            interpreter.write_output_line(&format!("=> {} : {}", value, value.type_name()))
+        }),
+        _ => interpreter.interpret(&stmts).map(|_| ()),
+    };
+    // 顶层的 return 只是提前结束这一行
+    match result.map_err(RuntimeError::into_stray_error) {
+        Ok(()) | Err(RuntimeError::Control(_)) => {}
+        Err(e) => {
+            let _ = interpreter.write_error_line(&format!("Runtime error: {}", e));
+        }
+    }
+}
+
+fn report(interpreter: &mut Interpreter, diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        let _ = interpreter.write_error_line(&diagnostic.to_string());
+    }
+}
@@ -0,0 +1,474 @@
+//! resolver.rs - 在执行之前静态解析变量引用。
+//! 遍历 AST，为每个局部变量的读取和赋值计算它所在的作用域与当前作用域之间的距离，
+//! 并写回到 `Expr::Variable` / `Expr::Assign` 的 `depth` 中。解释器按这个固定距离查找变量，
+//! 因此闭包总是看到定义时所在作用域里的绑定，而不会被之后同名的声明影响。
+//!
+//! 这里的作用域结构必须与解释器运行时创建的环境一一对应。
+//!
+//! 开启 `set_warn_recursion` 后还会检查函数对自身的调用是否位于尾部位置，
+//! 非尾部的递归调用在输入较大时可能耗尽调用栈，会产生一条警告。
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{Expr, Stmt};
+use crate::diagnostic::{Diagnostic, Stage};
+use crate::token::Token;
+
+/// 当前正在解析的代码所处的类，用于检查 `this` 和 `super` 的使用位置。
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    /// 有超类的类，其中可以使用 `super`
+    Subclass,
+    /// 静态方法和静态字段的初始化表达式，其中没有 `this` 和 `super`
+    Static,
+}
+
+pub struct Resolver {
+    /// 局部作用域栈，不包括全局作用域。值为 false 表示变量已声明、但初始化表达式还没有解析完
+    scopes: Vec<HashMap<String, bool>>,
+    /// 正在解析初始化表达式的全局变量，全局作用域不在 `scopes` 中，需要单独记录
+    initializing_global: Option<String>,
+    /// 已经定义的全局变量。用已有的全局变量重新声明同名变量（`var a = a + 1;`）是允许的
+    globals: HashSet<String>,
+    current_class: ClassType,
+    /// 正在解析的具名函数，用于识别递归调用。匿名函数和方法中为 None
+    current_function: Option<String>,
+    /// 位于 `return` 尾部位置的调用表达式，按地址区分
+    tail_calls: Vec<*const Expr>,
+    /// 当前函数内包围着正在解析的代码的带标签块，由外向内
+    labels: Vec<String>,
+    /// 当前函数内包围着正在解析的代码的循环层数
+    loop_depth: usize,
+    warn_recursion: bool,
+    warnings: Vec<Diagnostic>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Resolver {
+            scopes: Vec::new(),
+            initializing_global: None,
+            globals: HashSet::new(),
+            current_class: ClassType::None,
+            current_function: None,
+            tail_calls: Vec::new(),
+            labels: Vec::new(),
+            loop_depth: 0,
+            warn_recursion: false,
+            warnings: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// 设置是否对非尾部位置的递归调用发出警告。
+    pub fn set_warn_recursion(&mut self, enabled: bool) {
+        self.warn_recursion = enabled;
+    }
+
+    /// 告诉 resolver 哪些全局变量在运行时已经存在，例如原生函数和 REPL 之前输入的定义。
+    pub fn set_known_globals(&mut self, names: impl IntoIterator<Item = String>) {
+        self.globals.extend(names);
+    }
+
+    /// 解析过程中产生的警告。警告不会让 `resolve` 失败。
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    /// 解析一段顶层语句。顶层语句位于全局作用域，其中的变量不会被标注深度。
+    /// 发现静态错误时返回收集到的全部错误。
+    pub fn resolve(&mut self, stmts: &[Stmt]) -> Result<(), Vec<Diagnostic>> {
+        self.resolve_stmts(stmts);
+        self.finish()
+    }
+
+    /// 解析单个表达式，用于只求值一个表达式的场景（如 `evaluate` 命令）。
+    pub fn resolve_expression(&mut self, expr: &Expr) -> Result<(), Vec<Diagnostic>> {
+        self.resolve_expr(expr);
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Result<(), Vec<Diagnostic>> {
+        if self.diagnostics.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.diagnostics))
+        }
+    }
+
+    fn resolve_stmts(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.resolve_stmt(stmt);
+        }
+    }
+
+    fn resolve_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Print { expression } | Stmt::Expression { expression } => {
+                self.resolve_expr(expression);
+            }
+            Stmt::Var { name, initializer } => {
+                // 先声明再解析初始化表达式，这样才能发现 `var a = a;` 这种读取自身的情况
+                self.declare(name);
+                if let Some(initializer) = initializer {
+                    if self.scopes.is_empty() && !self.globals.contains(&name.lexeme) {
+                        self.initializing_global = Some(name.lexeme.clone());
+                    }
+                    self.resolve_expr(initializer);
+                    self.initializing_global = None;
+                }
+                self.define(name);
+            }
+            Stmt::Block { statements } => {
+                self.begin_scope();
+                self.resolve_stmts(statements);
+                self.end_scope();
+            }
+            Stmt::If { condition, then_branch, else_branch, .. } => {
+                self.resolve_expr(condition);
+                self.resolve_stmt(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.resolve_stmt(else_branch);
+                }
+            }
+            Stmt::While { binding, condition, body, .. } => {
+                // 条件在循环外的作用域求值，每次迭代的循环体（以及绑定的变量）有自己的作用域
+                self.resolve_expr(condition);
+                self.begin_scope();
+                if let Some(binding) = binding {
+                    self.define(binding);
+                }
+                self.resolve_loop_body(body);
+                self.end_scope();
+            }
+            Stmt::ForEach { index_name, var_name, iterable, body, .. } => {
+                self.resolve_expr(iterable);
+                self.begin_scope();
+                if let Some(index_name) = index_name {
+                    self.define(index_name);
+                }
+                self.define(var_name);
+                self.resolve_loop_body(body);
+                self.end_scope();
+            }
+            Stmt::For { initializer, condition, increment, body, .. } => {
+                // 初始化、条件、循环体和递增语句共享同一个作用域
+                self.begin_scope();
+                if let Some(initializer) = initializer {
+                    self.resolve_stmt(initializer);
+                }
+                if let Some(condition) = condition {
+                    self.resolve_expr(condition);
+                }
+                self.resolve_loop_body(body);
+                if let Some(increment) = increment {
+                    self.resolve_stmt(increment);
+                }
+                self.end_scope();
+            }
+            Stmt::Break { name, label, value } => {
+                match label {
+                    // break 不能跨越函数边界，因此只查找当前函数内的标签
+                    Some(label) if !self.labels.contains(&label.lexeme) => {
+                        self.error(label, &format!("No enclosing block labeled '{}'.", label.lexeme));
+                    }
+                    None if self.loop_depth == 0 => self.error(name, "Can't use 'break' outside of a loop."),
+                    _ => {}
+                }
+                if let Some(value) = value {
+                    self.resolve_expr(value);
+                }
+            }
+            Stmt::Continue { name } => {
+                if self.loop_depth == 0 {
+                    self.error(name, "Can't use 'continue' outside of a loop.");
+                }
+            }
+            Stmt::Function { name, params, rest, body } => {
+                // 先定义函数名，函数体内才能递归调用自己
+                self.define(name);
+                self.resolve_function(Some(name), params, rest.as_ref(), body);
+            }
+            Stmt::Class { name, superclass, methods, statics } => {
+                self.define(name);
+                // 静态成员直接在类声明所在的环境中求值和创建闭包，不经过 `super` 和 `this` 环境
+                let enclosing_class = std::mem::replace(&mut self.current_class, ClassType::Static);
+                for member in statics {
+                    match member {
+                        Stmt::Function { params, rest, body, .. } => self.resolve_function(None, params, rest.as_ref(), body),
+                        Stmt::Var { initializer: Some(initializer), .. } => self.resolve_expr(initializer),
+                        _ => {}
+                    }
+                }
+                self.current_class = ClassType::Class;
+                if let Some(superclass) = superclass {
+                    if let Expr::Variable { name: superclass_name, .. } = superclass {
+                        if superclass_name.lexeme == name.lexeme {
+                            self.error(superclass_name, "A class can't inherit from itself.");
+                        }
+                    }
+                    self.current_class = ClassType::Subclass;
+                    self.resolve_expr(superclass);
+                    // 对应解释器为方法闭包额外创建的 `super` 环境
+                    self.begin_scope();
+                    self.scopes.last_mut().unwrap().insert("super".to_string(), true);
+                }
+                // 方法绑定到实例时会创建一个只包含 `this` 的环境，位于方法闭包和参数环境之间
+                self.begin_scope();
+                self.scopes.last_mut().unwrap().insert("this".to_string(), true);
+                for method in methods {
+                    if let Stmt::Function { params, rest, body, .. } = method {
+                        self.resolve_function(None, params, rest.as_ref(), body);
+                    }
+                }
+                self.end_scope();
+                if superclass.is_some() {
+                    self.end_scope();
+                }
+                self.current_class = enclosing_class;
+            }
+            Stmt::Return { value, .. } => {
+                if let Some(value) = value {
+                    self.mark_tail_calls(value);
+                    self.resolve_expr(value);
+                }
+            }
+            Stmt::Using { var_name, initializer, body, .. } => {
+                // 与解释器一致：初始化表达式和循环体都在 using 自己的作用域中
+                self.begin_scope();
+                self.resolve_expr(initializer);
+                self.define(var_name);
+                self.resolve_stmt(body);
+                self.end_scope();
+            }
+            Stmt::Match { subject, arms, .. } => {
+                self.resolve_expr(subject);
+                for arm in arms {
+                    self.begin_scope();
+                    for name in arm.pattern.bindings() {
+                        self.define(name);
+                    }
+                    self.resolve_stmt(&arm.body);
+                    self.end_scope();
+                }
+            }
+        }
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Unary { right, .. } => self.resolve_expr(right),
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            }
+            Expr::Grouping { expression } => self.resolve_expr(expression),
+            Expr::Literal { .. } => {}
+            Expr::Variable { name, depth } => {
+                let uninitialized = match self.scopes.last() {
+                    Some(scope) => scope.get(&name.lexeme) == Some(&false),
+                    // 初始化表达式中的函数体有自己的作用域，因此在函数里引用正在定义的全局变量（递归）不受影响
+                    None => self.initializing_global.as_deref() == Some(name.lexeme.as_str()),
+                };
+                if uninitialized {
+                    self.error(name, "Can't read local variable in its own initializer.");
+                }
+                depth.set(self.resolve_local(name));
+            }
+            Expr::Assign { name, value, depth } => {
+                self.resolve_expr(value);
+                depth.set(self.resolve_local(name));
+            }
+            Expr::MinMaxAssign { target, value, .. } => {
+                self.resolve_expr(value);
+                self.resolve_expr(target);
+            }
+            Expr::Ternary { condition, then_branch, else_branch }
+            | Expr::IfExpr { condition, then_branch, else_branch } => {
+                self.resolve_expr(condition);
+                self.resolve_expr(then_branch);
+                self.resolve_expr(else_branch);
+            }
+            Expr::Call { callee, paren, arguments } => {
+                if self.warn_recursion && self.is_recursive_call(callee) && !self.tail_calls.contains(&(expr as *const Expr)) {
+                    let text = format!("[line {}] Warning: non-tail recursive call may overflow.", paren.line);
+                    self.warnings.push(Diagnostic::new(Stage::Resolve, Some(paren.line), text).with_column(paren.column));
+                }
+                self.resolve_expr(callee);
+                for argument in arguments {
+                    match argument {
+                        Expr::Spread { expression, .. } => self.resolve_expr(expression),
+                        argument => self.resolve_expr(argument),
+                    }
+                }
+            }
+            Expr::Function { params, rest, body, .. } => self.resolve_function(None, params, rest.as_ref(), body),
+            Expr::LabeledBlock { label, statements } => {
+                self.labels.push(label.lexeme.clone());
+                self.begin_scope();
+                self.resolve_stmts(statements);
+                self.end_scope();
+                self.labels.pop();
+            }
+            Expr::ListLiteral { elements, .. } => {
+                for element in elements {
+                    self.resolve_expr(element);
+                }
+            }
+            // 括号列表只能出现在并行赋值的两侧，由 `MultiAssign` 分支直接处理
+            Expr::Tuple { paren, elements } => {
+                self.error(paren, "Parenthesized lists can only be used in parallel assignment.");
+                for element in elements {
+                    self.resolve_expr(element);
+                }
+            }
+            Expr::Index { object, index, .. } => {
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+            }
+            Expr::SetIndex { object, index, value, .. } => {
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+                self.resolve_expr(value);
+            }
+            // 展开只能出现在调用实参中，由 `Call` 分支直接处理
+            Expr::Spread { ellipsis, expression } => {
+                self.error(ellipsis, "Spread is only allowed in call arguments.");
+                self.resolve_expr(expression);
+            }
+            Expr::This { keyword, depth } => {
+                match self.current_class {
+                    ClassType::None => self.error(keyword, "Can't use 'this' outside of a class."),
+                    ClassType::Static => self.error(keyword, "Can't use 'this' in a static member."),
+                    ClassType::Class | ClassType::Subclass => {}
+                }
+                depth.set(self.resolve_local(keyword));
+            }
+            Expr::Super { keyword, depth, .. } => {
+                match self.current_class {
+                    ClassType::None => self.error(keyword, "Can't use 'super' outside of a class."),
+                    ClassType::Class => self.error(keyword, "Can't use 'super' in a class with no superclass."),
+                    ClassType::Static => self.error(keyword, "Can't use 'super' in a static member."),
+                    ClassType::Subclass => {}
+                }
+                depth.set(self.resolve_local(keyword));
+            }
+            Expr::Get { object, .. } => self.resolve_expr(object),
+            Expr::Set { object, value, .. } => {
+                self.resolve_expr(value);
+                self.resolve_expr(object);
+            }
+            // 并行赋值的目标在运行时沿作用域链查找
+            Expr::MultiAssign { names, depths, value, .. } => {
+                match &**value {
+                    Expr::Tuple { elements, .. } => {
+                        for element in elements {
+                            self.resolve_expr(element);
+                        }
+                    }
+                    value => self.resolve_expr(value),
+                }
+                for (name, depth) in names.iter().zip(depths) {
+                    depth.set(self.resolve_local(name));
+                }
+            }
+        }
+    }
+
+    /// 函数的参数和函数体语句位于同一个作用域，对应 `UserFunction::call` 创建的环境。
+    fn resolve_function(&mut self, name: Option<&Token>, params: &[Token], rest: Option<&Token>, body: &Stmt) {
+        let enclosing_function = std::mem::replace(&mut self.current_function, name.map(|name| name.lexeme.clone()));
+        // 函数体中的 break / continue 不能作用于函数外的循环和带标签块
+        let enclosing_labels = std::mem::take(&mut self.labels);
+        let enclosing_loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        self.begin_scope();
+        for param in params.iter().chain(rest) {
+            self.define(param);
+        }
+        match body {
+            Stmt::Block { statements } => self.resolve_stmts(statements),
+            other => self.resolve_stmt(other),
+        }
+        self.end_scope();
+        self.labels = enclosing_labels;
+        self.loop_depth = enclosing_loop_depth;
+        self.current_function = enclosing_function;
+    }
+
+    /// 解析循环体，其中可以使用 break 和 continue。
+    fn resolve_loop_body(&mut self, body: &Stmt) {
+        self.loop_depth += 1;
+        self.resolve_stmt(body);
+        self.loop_depth -= 1;
+    }
+
+    /// 记录 `return` 值中处于尾部位置的调用：调用本身，或条件表达式、逻辑运算右侧的尾部调用。
+    fn mark_tail_calls(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Call { .. } => self.tail_calls.push(expr),
+            Expr::Grouping { expression } => self.mark_tail_calls(expression),
+            Expr::Logical { right, .. } => self.mark_tail_calls(right),
+            Expr::Ternary { then_branch, else_branch, .. }
+            | Expr::IfExpr { then_branch, else_branch, .. } => {
+                self.mark_tail_calls(then_branch);
+                self.mark_tail_calls(else_branch);
+            }
+            _ => {}
+        }
+    }
+
+    /// 被调用者是否就是正在解析的具名函数。
+    fn is_recursive_call(&self, callee: &Expr) -> bool {
+        match (callee, &self.current_function) {
+            (Expr::Variable { name, .. }, Some(function)) => &name.lexeme == function,
+            _ => false,
+        }
+    }
+
+    /// 从内向外查找变量所在的局部作用域，返回距离；找不到时视为全局变量。
+    fn resolve_local(&self, name: &Token) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(&name.lexeme))
+    }
+
+    fn declare(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), false);
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        match self.scopes.last_mut() {
+            Some(scope) => {
+                scope.insert(name.lexeme.clone(), true);
+            }
+            None => {
+                self.globals.insert(name.lexeme.clone());
+            }
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        let text = format!("[line {}] Error at '{}': {}", token.line, token.lexeme, message);
+        self.diagnostics.push(Diagnostic::new(Stage::Resolve, Some(token.line), text).with_column(token.column));
+    }
+}
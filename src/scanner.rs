@@ -1,6 +1,7 @@
 //! scanner.rs - 负责将源代码字符串转换为 Token 序列。
 //! 这是解释器的词法分析阶段。
 
+use crate::diagnostic::{Diagnostic, Stage};
 use crate::token::{Literal, Token, TokenType};
 use std::iter::Peekable;
 use std::str::Chars;
@@ -14,8 +15,14 @@ pub struct Scanner<'a> {
     tokens: Vec<Token>,
     /// 当前所在的行号，用于错误报告。
     line: usize,
-    /// 记录在扫描过程中是否遇到了错误。
-    had_error: bool,
+    /// 已经消耗的字符数。
+    offset: usize,
+    /// 当前行第一个字符的位置（以已消耗的字符数计）。
+    line_start: usize,
+    /// 正在扫描的 Token 第一个字符的列号，从 1 开始。
+    start_column: usize,
+    /// 扫描过程中遇到的错误。
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl<'a> Scanner<'a> {
@@ -26,21 +33,49 @@ impl<'a> Scanner<'a> {
             chars: source.chars().peekable(),
             tokens: Vec::new(),
             line: 1,
-            had_error: false,
+            offset: 0,
+            line_start: 0,
+            start_column: 1,
+            diagnostics: Vec::new(),
         }
     }
 
     /// 扫描整个源代码，并返回生成的 Token 列表以及是否发生错误的标志。
-    /// 此方法会消耗 Scanner 实例。
-    pub fn scan_tokens(mut self) -> (Vec<Token>, bool) {
+    /// 遇到的错误会输出到 stderr。此方法会消耗 Scanner 实例。
+    pub fn scan_tokens(self) -> (Vec<Token>, bool) {
+        let (tokens, diagnostics) = self.scan_tokens_with_diagnostics();
+        for diagnostic in &diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+        (tokens, !diagnostics.is_empty())
+    }
+
+    /// 扫描整个源代码，返回 Token 列表和收集到的错误，不向 stderr 输出任何内容。
+    pub fn scan_tokens_with_diagnostics(mut self) -> (Vec<Token>, Vec<Diagnostic>) {
         // 主扫描循环，只要还有字符就继续。
-        while let Some(c) = self.advance() {
+        loop {
+            self.start_column = self.offset - self.line_start + 1;
+            let Some(c) = self.advance() else { break };
             self.scan_token(c);
         }
 
         // 扫描结束后，添加一个文件结束符（Eof）Token。
-        self.tokens.push(Token::new(TokenType::Eof, "".to_string(), None, self.line));
-        (self.tokens, self.had_error)
+        self.tokens.push(Token::new(TokenType::Eof, "".to_string(), None, self.line, self.start_column));
+        (self.tokens, self.diagnostics)
+    }
+
+    /// 记录一个扫描错误。
+    fn error(&mut self, message: &str) {
+        self.error_at(self.line, message);
+    }
+
+    /// 记录一个发生在指定行的扫描错误，用于跨行结构报告其起始行。
+    /// 列号是出错的 Token 第一个字符所在的列。
+    fn error_at(&mut self, line: usize, message: &str) {
+        self.diagnostics.push(
+            Diagnostic::new(Stage::Scan, Some(line), format!("[line {}] Error: {}", line, message))
+                .with_column(self.start_column),
+        );
     }
 
     /// 根据当前字符扫描并处理单个 Token。
@@ -50,12 +85,26 @@ impl<'a> Scanner<'a> {
             ')' => self.add_chars_token(TokenType::RightParen, ")"),
             '{' => self.add_chars_token(TokenType::LeftBrace, "{"),
             '}' => self.add_chars_token(TokenType::RightBrace, "}"),
+            '[' => self.add_chars_token(TokenType::LeftBracket, "["),
+            ']' => self.add_chars_token(TokenType::RightBracket, "]"),
             ',' => self.add_chars_token(TokenType::Comma, ","),
-            '.' => self.add_chars_token(TokenType::Dot, "."),
+            '.' => {
+                let mut ahead = self.chars.clone();
+                if ahead.next() == Some('.') && ahead.next() == Some('.') {
+                    self.advance();
+                    self.advance();
+                    self.add_chars_token(TokenType::Ellipsis, "...");
+                } else {
+                    self.add_chars_token(TokenType::Dot, ".");
+                }
+            }
             '-' => self.add_chars_token(TokenType::Minus, "-"),
             '+' => self.add_chars_token(TokenType::Plus, "+"),
             ';' => self.add_chars_token(TokenType::Semicolon, ";"),
+            ':' => self.add_chars_token(TokenType::Colon, ":"),
             '*' => self.add_chars_token(TokenType::Star, "*"),
+            '%' => self.add_chars_token(TokenType::Percent, "%"),
+            '?' => self.add_chars_token(TokenType::Question, "?"),
 
             // 处理可能为双字符的 Token
             '!' => {
@@ -63,7 +112,13 @@ impl<'a> Scanner<'a> {
                 self.add_chars_token(ty, lexeme);
             },
             '=' => {
-                let (ty, lexeme) = if self.match_char('=') { (TokenType::EqualEqual, "==") } else { (TokenType::Equal, "=") };
+                let (ty, lexeme) = if self.match_char('=') {
+                    (TokenType::EqualEqual, "==")
+                } else if self.match_char('>') {
+                    (TokenType::FatArrow, "=>")
+                } else {
+                    (TokenType::Equal, "=")
+                };
                 self.add_chars_token(ty, lexeme);
             },
             '<' => {
@@ -82,6 +137,8 @@ impl<'a> Scanner<'a> {
                         if pc == '\n' { break; }
                         self.advance();
                     }
+                } else if self.match_char('*') {
+                    self.block_comment();
                 } else {
                     self.add_chars_token(TokenType::Slash, "/");
                 }
@@ -103,15 +160,19 @@ impl<'a> Scanner<'a> {
 
             // 未知字符
             c => {
-                eprintln!("[line {}] Error: Unexpected character: {}", self.line, c);
-                self.had_error = true;
+                self.error(&format!("Unexpected character: {}", c));
             }
         }
     }
 
     /// 消费迭代器中的下一个字符并返回它。
     fn advance(&mut self) -> Option<char> {
-        self.chars.next()
+        let c = self.chars.next()?;
+        self.offset += 1;
+        if c == '\n' {
+            self.line_start = self.offset;
+        }
+        Some(c)
     }
 
     /// 查看迭代器中的下一个字符，但不消耗它。
@@ -131,26 +192,78 @@ impl<'a> Scanner<'a> {
 
     /// 添加一个简单的单字符（或双字符）Token。
     fn add_chars_token(&mut self, token_type: TokenType, lexeme: &str) {
-        self.tokens.push(Token::new(token_type, lexeme.to_string(), None, self.line));
+        self.tokens.push(Token::new(token_type, lexeme.to_string(), None, self.line, self.start_column));
     }
 
     /// 添加一个带有字面量值的 Token。
     fn add_literal_token(&mut self, token_type: TokenType, lexeme: String, literal: Option<Literal>) {
-        self.tokens.push(Token::new(token_type, lexeme, literal, self.line));
+        self.tokens.push(Token::new(token_type, lexeme, literal, self.line, self.start_column));
+    }
+
+    /// 跳过块注释 `/* ... */`，开头的 `/*` 已被消耗。
+    /// 块注释可以嵌套，`/* a /* b */ c */` 整体都是注释。
+    fn block_comment(&mut self) {
+        let start_line = self.line;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.advance() {
+                Some('/') if self.peek() == Some('*') => {
+                    self.advance();
+                    depth += 1;
+                }
+                Some('*') if self.peek() == Some('/') => {
+                    self.advance();
+                    depth -= 1;
+                }
+                Some('\n') => self.line += 1,
+                Some(_) => (),
+                None => {
+                    self.error_at(start_line, "Unterminated block comment.");
+                    return;
+                }
+            }
+        }
     }
 
     /// 处理字符串字面量。
+    /// 支持转义序列 `\n` `\t` `\r` `\\` `\"` `\0` 以及 `\u{1F600}` 这样的 1 到 6 位十六进制码点，
+    /// 词素保留源码原样，字面量值为解码后的字符串。
     fn string(&mut self) {
+        let mut raw = String::new();
         let mut value = String::new();
         while let Some(c) = self.peek() {
             if c == '"' { break; }
             if c == '\n' { self.line += 1; }
-            value.push(self.advance().unwrap());
+            raw.push(self.advance().unwrap());
+            if c != '\\' {
+                value.push(c);
+                continue;
+            }
+            // 转义序列：反斜杠后面的字符决定实际写入的字符
+            let Some(escaped) = self.advance() else { break };
+            raw.push(escaped);
+            match escaped {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                'r' => value.push('\r'),
+                '\\' => value.push('\\'),
+                '"' => value.push('"'),
+                '0' => value.push('\0'),
+                'u' => match self.unicode_escape(&mut raw) {
+                    Some(decoded) => value.push(decoded),
+                    None => self.error("Invalid unicode escape sequence."),
+                },
+                other => {
+                    if other == '\n' { self.line += 1; }
+                    self.error(&format!("Unknown escape sequence '\\{}'.", other));
+                    value.push('\\');
+                    value.push(other);
+                }
+            }
         }
 
         if self.peek().is_none() {
-            eprintln!("[line {}] Error: Unterminated string.", self.line);
-            self.had_error = true;
+            self.error("Unterminated string.");
             return;
         }
 
@@ -158,19 +271,38 @@ impl<'a> Scanner<'a> {
         self.advance();
 
         // 完整的词素包括引号
-        let lexeme = format!("\"{}\"", value);
+        let lexeme = format!("\"{}\"", raw);
         self.add_literal_token(TokenType::String, lexeme, Some(Literal::String(value)));
     }
 
+    /// 读取 `\u` 之后的 `{十六进制}` 部分并追加到 `raw`，码点不合法时返回 None。
+    /// 遇到 `}`、引号或换行就停下，不会吞掉字符串的结尾。
+    fn unicode_escape(&mut self, raw: &mut String) -> Option<char> {
+        if self.peek() != Some('{') {
+            return None;
+        }
+        raw.push(self.advance().unwrap());
+        let mut digits = String::new();
+        while let Some(c) = self.peek() {
+            if c == '}' || c == '"' || c == '\n' { break; }
+            digits.push(c);
+            raw.push(self.advance().unwrap());
+        }
+        if !self.match_char('}') {
+            return None;
+        }
+        raw.push('}');
+        if digits.is_empty() || digits.len() > 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32)
+    }
+
     /// 处理数字字面量。
     fn number(&mut self, first_char: char) {
         let mut lexeme = String::new();
         lexeme.push(first_char);
-
-        while let Some(c) = self.peek() {
-            if !c.is_ascii_digit() { break; }
-            lexeme.push(self.advance().unwrap());
-        }
+        self.digits(&mut lexeme);
 
         if self.peek() == Some('.') {
             let mut ahead = self.chars.clone();
@@ -178,16 +310,49 @@ impl<'a> Scanner<'a> {
             if let Some(next_char) = ahead.peek() {
                 if next_char.is_ascii_digit() {
                     lexeme.push(self.advance().unwrap()); // 消耗 '.'
-                    while let Some(c) = self.peek() {
-                        if !c.is_ascii_digit() { break; }
-                        lexeme.push(self.advance().unwrap());
-                    }
+                    self.digits(&mut lexeme);
                 }
             }
         }
 
-        let value: f64 = lexeme.parse().unwrap();
-        self.add_literal_token(TokenType::Number, lexeme, Some(Literal::Number(value)));
+        // 指数部分：`e`/`E`，可选的正负号，再跟至少一位数字；否则 `e` 留给后面的标识符
+        if let Some('e' | 'E') = self.peek() {
+            let mut ahead = self.chars.clone();
+            ahead.next(); // 跳过 'e'
+            let sign = ahead.next_if(|c| *c == '+' || *c == '-');
+            if ahead.peek().is_some_and(|c| c.is_ascii_digit()) {
+                lexeme.push(self.advance().unwrap()); // 消耗 'e'
+                if sign.is_some() {
+                    lexeme.push(self.advance().unwrap());
+                }
+                self.digits(&mut lexeme);
+            }
+        }
+
+        // 没有小数部分和指数部分且能放进 i64 的字面量作为整数，其余的按浮点数处理
+        // 下划线只能出现在两个数字之间，Token 的 lexeme 保留原文，解析数值前去掉下划线
+        let bytes = lexeme.as_bytes();
+        let misplaced = bytes.iter().enumerate().any(|(i, &b)| {
+            b == b'_' && !(i > 0 && bytes[i - 1].is_ascii_digit() && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+        });
+        if misplaced {
+            self.error(&format!("Invalid digit separator in number '{}'.", lexeme));
+            return;
+        }
+        let digits = lexeme.replace('_', "");
+        let literal = match digits.parse::<i64>() {
+            Ok(value) if !digits.contains('.') => Literal::Integer(value),
+            _ => Literal::Number(digits.parse().unwrap()),
+        };
+        self.add_literal_token(TokenType::Number, lexeme, Some(literal));
+    }
+
+    /// 连续读取数字和数字分隔符 `_`，追加到 lexeme 末尾。
+    fn digits(&mut self, lexeme: &mut String) {
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_digit() && c != '_' { break; }
+            lexeme.push(self.advance().unwrap());
+        }
     }
 
     /// 处理标识符和关键字。
@@ -207,10 +372,12 @@ impl<'a> Scanner<'a> {
             "and" => TokenType::And,
             "class" => TokenType::Class,
             "else" => TokenType::Else,
+            "elif" => TokenType::Elif,
             "false" => TokenType::False,
             "for" => TokenType::For,
             "fun" => TokenType::Fun,
             "if" => TokenType::If,
+            "in" => TokenType::In,
             "nil" => TokenType::Nil,
             "or" => TokenType::Or,
             "print" => TokenType::Print,
@@ -222,6 +389,8 @@ impl<'a> Scanner<'a> {
             "while" => TokenType::While,
             "break" => TokenType::Break,
             "continue" => TokenType::Continue,
+            "match" => TokenType::Match,
+            "using" => TokenType::Using,
             _ => TokenType::Identifier,
         };
         self.add_chars_token(token_type, &lexeme);
@@ -9,13 +9,17 @@ use std::fmt::{self, Display};
 pub enum TokenType {
     // 单字符 Token。
     LeftParen, RightParen, LeftBrace, RightBrace, // ( ) { }
+    LeftBracket, RightBracket, // [ ]
     Comma, Dot, Minus, Plus, Semicolon, Slash, Star, // , . - + ; / *
+    Colon, Percent, Question, // : % ?
 
     // 一个或两个字符的 Token。
     Bang, BangEqual,     // ! !=
     Equal, EqualEqual,   // = ==
+    FatArrow,            // =>
     Greater, GreaterEqual, // > >=
     Less, LessEqual,     // < <=
+    Ellipsis,            // ...
 
     // 字面量。
     Identifier, // 标识符
@@ -23,8 +27,8 @@ pub enum TokenType {
     Number,     // 数字
 
     // 关键字。
-    And, Class, Else, False, Fun, For, If, Nil, Or,
-    Print, Return, Super, This, True, Var, While,Break, Continue,
+    And, Class, Else, Elif, False, Fun, For, If, In, Nil, Or,
+    Print, Return, Super, This, True, Var, While,Break, Continue, Match, Using,
 
     Eof // 文件结束符
 }
@@ -40,6 +44,8 @@ pub struct Token {
     pub literal: Option<Literal>,
     /// Token 所在的行号，用于错误报告。
     pub line: usize,
+    /// Token 第一个字符在行内的列号，从 1 开始，按字符计数。
+    pub column: usize,
 }
 
 /// Literal 枚举表示字面量的值。
@@ -47,6 +53,8 @@ pub struct Token {
 pub enum Literal {
     String(String),
     Number(f64),
+    /// 不带小数点的数字字面量，求值为 `MskValue::Int`
+    Integer(i64),
 }
 impl Literal {
     /// 获取字面量的字符串表示。
@@ -54,23 +62,13 @@ impl Literal {
         match self {
             Literal::String(s) => s.clone(),
             Literal::Number(n) => n.to_string(),
+            Literal::Integer(n) => n.to_string(),
         }
     }
 }
 impl Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let token_type_str = format!("{:?}", self.token_type).to_uppercase();
-        let token_type: String = match token_type_str.as_str() {
-            "LEFTPAREN" => "LEFT_PAREN".to_string(),
-            "RIGHTPAREN" => "RIGHT_PAREN".to_string(),
-            "LEFTBRACE" => "LEFT_BRACE".to_string(),
-            "RIGHTBRACE" => "RIGHT_BRACE".to_string(),
-            "EQUALEQUAL" => "EQUAL_EQUAL".to_string(),
-            "BANGEQUAL" => "BANG_EQUAL".to_string(),
-            "GREATEREQUAL" => "GREATER_EQUAL".to_string(),
-            "LESSEQUAL" => "LESS_EQUAL".to_string(),
-            s => s.to_string(),
-        };
+        let token_type = self.type_name();
         let lexeme = &self.lexeme;
         let literal = match &self.literal {
             Some(Literal::Number(n)) => {
@@ -80,6 +78,8 @@ impl Display for Token {
                     format!("{}", n)
                 }
             },
+            // 保持与浮点数相同的输出格式，`tokenize` 的结果不受整数字面量影响
+            Some(Literal::Integer(n)) => format!("{}.0", n),
             Some(Literal::String(s)) => s.clone(),
             None => "null".to_string(),
         };
@@ -89,12 +89,79 @@ impl Display for Token {
 
 impl Token {
     /// 创建一个新的 Token 实例。
-    pub fn new(token_type: TokenType, lexeme: String, literal: Option<Literal>, line: usize) -> Self {
+    pub fn new(token_type: TokenType, lexeme: String, literal: Option<Literal>, line: usize, column: usize) -> Self {
         Token {
             token_type,
             lexeme,
             literal,
             line,
+            column,
+        }
+    }
+
+    /// Token 类型的大写下划线名称，例如 `LEFT_PAREN`。
+    pub fn type_name(&self) -> String {
+        let token_type_str = format!("{:?}", self.token_type).to_uppercase();
+        match token_type_str.as_str() {
+            "LEFTPAREN" => "LEFT_PAREN".to_string(),
+            "RIGHTPAREN" => "RIGHT_PAREN".to_string(),
+            "LEFTBRACE" => "LEFT_BRACE".to_string(),
+            "RIGHTBRACE" => "RIGHT_BRACE".to_string(),
+            "LEFTBRACKET" => "LEFT_BRACKET".to_string(),
+            "RIGHTBRACKET" => "RIGHT_BRACKET".to_string(),
+            "EQUALEQUAL" => "EQUAL_EQUAL".to_string(),
+            "FATARROW" => "FAT_ARROW".to_string(),
+            "BANGEQUAL" => "BANG_EQUAL".to_string(),
+            "GREATEREQUAL" => "GREATER_EQUAL".to_string(),
+            "LESSEQUAL" => "LESS_EQUAL".to_string(),
+            s => s.to_string(),
+        }
+    }
+
+    /// 将 Token 序列化为 JSON 对象，包含 `type`、`lexeme`、`literal`、`line` 和 `column` 字段。
+    /// 供编辑器等外部工具使用，比 `Display` 的输出更容易解析。
+    pub fn to_json(&self) -> String {
+        let literal = match &self.literal {
+            // JSON 无法表示无穷大和 NaN，例如溢出的 `1e999`，这里输出 null
+            Some(Literal::Number(n)) if !n.is_finite() => "null".to_string(),
+            Some(Literal::Number(n)) => {
+                if n.fract() == 0.0 {
+                    format!("{0:.1}", n)
+                } else {
+                    format!("{}", n)
+                }
+            },
+            // 保持与浮点数相同的输出格式，`tokenize` 的结果不受整数字面量影响
+            Some(Literal::Integer(n)) => format!("{}.0", n),
+            Some(Literal::String(s)) => json_string(s),
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"type\":{},\"lexeme\":{},\"literal\":{},\"line\":{},\"column\":{}}}",
+            json_string(&self.type_name()),
+            json_string(&self.lexeme),
+            literal,
+            self.line,
+            self.column
+        )
+    }
+}
+
+/// 将字符串转义为 JSON 字符串字面量（包含两侧引号）。
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
         }
     }
+    out.push('"');
+    out
 }
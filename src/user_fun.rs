@@ -4,36 +4,75 @@ use std::rc::Rc;
 use log::info;
 use crate::ast::Stmt;
 use crate::callable::Callable;
+use crate::control_flow::ControlFlow;
 use crate::environment::Environment;
-use crate::interpreter::{Interpreter, RuntimeError, ScopeGuard};
+use crate::interpreter::{check_arity, Interpreter, RuntimeError, ScopeGuard};
 use crate::msk_value::MskValue;
 use crate::token::Token;
 
 pub struct UserFunction {
     pub name: String,
     pub params: Vec<Token>,
+    /// 剩余参数，多出来的实参会收集成列表绑定到它
+    pub rest: Option<Token>,
     pub body: Rc<Stmt>,
     pub closure: Rc<RefCell<Environment>>,
 }
-impl Callable for UserFunction {
-    fn arity(&self) -> usize {
-        self.params.len()
-    }
-
-    fn call(&self, interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue,RuntimeError> {
-        if args.len() != self.arity() {
-            return Err(format!("Expected {} arguments but got {}.", self.arity(), args.len()).into());
+impl UserFunction {
+    /// 把方法绑定到实例上：返回的新函数在一个定义了 `this` 的环境中执行。
+    pub fn bind(&self, instance: MskValue) -> UserFunction {
+        let env = Environment::new_with_parent(self.closure.clone());
+        (*env).borrow_mut().define("this", instance);
+        UserFunction {
+            name: self.name.clone(),
+            params: self.params.clone(),
+            rest: self.rest.clone(),
+            body: self.body.clone(),
+            closure: env,
         }
+    }
 
-        let guard = ScopeGuard::new(interpreter);
+    /// 绑定参数并执行函数体。
+    fn call_body(&self, interpreter: &mut Interpreter, mut args: Vec<MskValue>) -> Result<MskValue,RuntimeError> {
+        // 函数体在定义时捕获的环境中执行，而不是调用处的环境
+        let guard = ScopeGuard::with_parent(interpreter, self.closure.clone());
+        let extra = args.split_off(self.arity());
         for (param, arg) in self.params.iter().zip(args) {
             (*guard.interpreter.env).borrow_mut().define(&param.lexeme, arg);
 
         }
+        if let Some(rest) = &self.rest {
+            (*guard.interpreter.env).borrow_mut().define(&rest.lexeme, MskValue::List(Rc::new(RefCell::new(extra))));
+        }
         if let Stmt::Block {statements} = &*self.body {
-            Ok(guard.interpreter.interpret(statements.as_slice())?)
+            match guard.interpreter.execute(statements.as_slice()) {
+                // 没有执行 return 语句就结束的函数返回 nil
+                Ok(_) => Ok(MskValue::Nil),
+                Err(RuntimeError::Control(ControlFlow::Return(value))) => Ok(value),
+                Err(e) => Err(e.into_stray_error()),
+            }
         } else {
             Err("Function body must be a block statement.".to_string().into())
         }
     }
-}
\ No newline at end of file
+}
+impl Callable for UserFunction {
+    fn arity(&self) -> usize {
+        self.params.len()
+    }
+    fn max_arity(&self) -> Option<usize> {
+        match self.rest {
+            Some(_) => None,
+            None => Some(self.arity()),
+        }
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue,RuntimeError> {
+        // 解释器的调用点已经检查过参数个数，这里再检查一次，宿主直接调用时也不会因为参数太少而 panic
+        check_arity(interpreter.call_line(), &self.name, self, args.len())?;
+        interpreter.enter_call()?;
+        let result = self.call_body(interpreter, args);
+        interpreter.exit_call();
+        result
+    }
+}
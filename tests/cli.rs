@@ -0,0 +1,146 @@
+//! 通过命令行运行解释器二进制文件的测试。
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// 把源码写入临时目录下的脚本文件，返回文件路径。
+fn script(name: &str, src: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("msk-cli-{}-{}.msk", std::process::id(), name));
+    fs::write(&path, src).unwrap();
+    path
+}
+
+/// 运行解释器，`stdin` 的内容作为标准输入。
+fn msk(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-interpreter"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn run_passes_extra_arguments_to_argv() {
+    let path = script("argv", "print argv();");
+    let output = msk(&["run", path.to_str().unwrap(), "a", "b c"], "");
+    assert_eq!(stdout(&output), "[a, b c]\n");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+}
+
+#[test]
+fn options_after_the_script_path_are_passed_to_the_script() {
+    let path = script("argv-flags", "print argv();");
+    let output = msk(&["run", path.to_str().unwrap(), "--asi", "--warn-recursion", "--"], "");
+    assert_eq!(stdout(&output), "[--asi, --warn-recursion, --]\n");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+}
+
+#[test]
+fn double_dash_ends_interpreter_options() {
+    let path = script("argv-dash", "print argv()\n");
+    let output = msk(&["run", "--asi", "--", path.to_str().unwrap(), "--asi"], "");
+    assert_eq!(stdout(&output), "[--asi]\n");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+}
+
+#[test]
+fn unknown_options_are_rejected() {
+    let path = script("unknown-option", "print 1;");
+    let output = msk(&["run", "--fast", path.to_str().unwrap()], "");
+    assert_eq!(stderr(&output), "Unknown option: --fast\n");
+    assert_eq!(output.status.code(), Some(64));
+}
+
+#[test]
+fn run_rejects_break_with_unknown_label() {
+    let path = script("stray-break", "print \"before\";\nbreak nolabel 3;");
+    let output = msk(&["run", path.to_str().unwrap()], "");
+    assert_eq!(stdout(&output), "");
+    assert_eq!(output.status.code(), Some(65));
+    assert!(stderr(&output).contains("No enclosing block labeled 'nolabel'."), "{}", stderr(&output));
+}
+
+#[test]
+fn run_with_asi_accepts_newline_terminated_statements() {
+    let path = script("asi", "var x = 1\nprint x\nprint x + 1\n");
+    let output = msk(&["run", "--asi", path.to_str().unwrap()], "");
+    assert_eq!(stdout(&output), "1\n2\n");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+}
+
+#[test]
+fn run_with_asi_keeps_a_max_assignment_on_its_own_line() {
+    let path = script("asi-max", "var max = 0\nvar x = 1\nprint x\nmax = 5\nprint max\n");
+    let output = msk(&["run", "--asi", path.to_str().unwrap()], "");
+    assert_eq!(stdout(&output), "1\n5\n");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+}
+
+#[test]
+fn read_line_reads_stdin_line_by_line() {
+    let path = script("read-line", "var line = read_line(); while (line != nil) { print \"<\" + line + \">\"; line = read_line(); }");
+    let output = msk(&["run", path.to_str().unwrap()], "first\nsecond line\r\nlast");
+    assert_eq!(stdout(&output), "<first>\n<second line>\n<last>\n");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+}
+
+#[test]
+fn read_line_returns_nil_on_empty_stdin() {
+    let path = script("read-line-eof", "print read_line();");
+    let output = msk(&["run", path.to_str().unwrap()], "");
+    assert_eq!(stdout(&output), "nil\n");
+}
+
+#[test]
+fn repl_exits_cleanly_at_end_of_input() {
+    let output = msk(&["repl"], "var x = 2;\nx * 3\n");
+    assert_eq!(stdout(&output), "> > => 6 : number\n> \n");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+}
+
+#[test]
+fn reading_a_variable_in_its_own_initializer_fails_to_resolve() {
+    let path = script("self-init", "var a = a;\n");
+    let output = msk(&["run", path.to_str().unwrap()], "");
+    assert_eq!(stderr(&output), "[line 1] Error at 'a': Can't read local variable in its own initializer.\n");
+    assert_eq!(output.status.code(), Some(65));
+}
+
+#[test]
+fn warn_recursion_reports_non_tail_calls_and_still_runs() {
+    let path = script("warn-recursion", "fun sum(n) {\n  if (n == 0) return 0;\n  return n + sum(n - 1);\n}\nprint sum(3);\n");
+    let output = msk(&["run", "--warn-recursion", path.to_str().unwrap()], "");
+    assert_eq!(stderr(&output), "[line 3] Warning: non-tail recursive call may overflow.\n");
+    assert_eq!(stdout(&output), "6\n");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn cli_allows_deeper_recursion_than_the_library_default() {
+    let path = script("deep", "fun depth(n) { if (n == 0) return 0; return 1 + depth(n - 1); }\nprint depth(500);\n");
+    let output = msk(&["run", path.to_str().unwrap()], "");
+    assert_eq!(stdout(&output), "500\n");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+}
+
+#[test]
+fn cli_reports_infinite_recursion_as_a_runtime_error() {
+    let path = script("infinite", "fun f(n) { return f(n + 1); }\nf(0);\n");
+    let output = msk(&["run", path.to_str().unwrap()], "");
+    assert_eq!(stderr(&output), "Runtime error: Stack overflow: maximum call depth exceeded.\n");
+    assert_eq!(output.status.code(), Some(70));
+}
@@ -0,0 +1,77 @@
+//! 集成测试共用的辅助函数：在输出被捕获的解释器中执行源码。
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use codecrafters_interpreter::control_flow::ControlFlow;
+use codecrafters_interpreter::diagnostic::Diagnostic;
+use codecrafters_interpreter::resolver::Resolver;
+use codecrafters_interpreter::{Interpreter, Parser, RuntimeError, Scanner};
+
+/// 写入的内容之后可以读回的缓冲区，克隆出的副本共享同一份数据。
+#[derive(Clone, Default)]
+pub struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub fn contents(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 创建一个把 `print` 输出写入缓冲区的解释器。
+pub fn interpreter() -> (Interpreter, SharedBuffer) {
+    let out = SharedBuffer::default();
+    (Interpreter::with_output(out.clone()), out)
+}
+
+/// 在给定的解释器中扫描、解析、静态解析并执行源码，任一阶段出错时返回错误信息。
+pub fn exec(interpreter: &mut Interpreter, src: &str) -> Result<(), String> {
+    let (tokens, diagnostics) = Scanner::new(src).scan_tokens_with_diagnostics();
+    if !diagnostics.is_empty() {
+        return Err(join(&diagnostics));
+    }
+    let stmts = Parser::new(tokens).parse_with_diagnostics().map_err(|d| join(&d))?;
+    let mut resolver = Resolver::new();
+    resolver.set_known_globals(interpreter.global_names());
+    resolver.resolve(&stmts).map_err(|d| join(&d))?;
+    match interpreter.interpret(&stmts) {
+        // 顶层的 return 只是提前结束程序
+        Ok(_) | Err(RuntimeError::Control(ControlFlow::Return(_))) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// 执行源码并返回全部 `print` 输出，出错时测试失败。
+pub fn run(src: &str) -> String {
+    let (mut interpreter, out) = interpreter();
+    if let Err(e) = exec(&mut interpreter, src) {
+        panic!("unexpected error: {}\noutput so far: {}", e, out.contents());
+    }
+    out.contents()
+}
+
+/// 执行源码并返回错误信息，执行成功时测试失败。
+pub fn run_err(src: &str) -> String {
+    let (mut interpreter, out) = interpreter();
+    match exec(&mut interpreter, src) {
+        Ok(()) => panic!("expected an error, got output: {}", out.contents()),
+        Err(e) => e,
+    }
+}
+
+fn join(diagnostics: &[Diagnostic]) -> String {
+    diagnostics.iter().map(|d| d.to_string()).collect::<Vec<_>>().join("\n")
+}
@@ -0,0 +1,304 @@
+//! 把解释器作为库嵌入时使用的接口。
+
+mod common;
+
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use codecrafters_interpreter::callable::Callable;
+use codecrafters_interpreter::diagnostic::Stage;
+use codecrafters_interpreter::{run_source, Interpreter, MskValue, Parser, RuntimeError, Scanner};
+use common::{exec, interpreter, SharedBuffer};
+
+/// 把源码写入临时目录下的脚本文件，返回文件路径。
+fn script(name: &str, src: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("msk-embed-{}-{}.msk", std::process::id(), name));
+    fs::write(&path, src).unwrap();
+    path
+}
+
+/// 由宿主闭包实现的原生函数。
+struct HostFn<F>(usize, F);
+
+impl<F: Fn(Vec<MskValue>) -> Result<MskValue, RuntimeError>> Callable for HostFn<F> {
+    fn arity(&self) -> usize {
+        self.0
+    }
+
+    fn call(&self, _interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        (self.1)(args)
+    }
+}
+
+/// 只有被刷新的内容才算真正写出的缓冲区，用来检查 autoflush。
+#[derive(Clone, Default)]
+struct FlushTracker {
+    pending: Rc<RefCell<Vec<u8>>>,
+    flushed: Rc<RefCell<String>>,
+}
+
+impl Write for FlushTracker {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let pending = std::mem::take(&mut *self.pending.borrow_mut());
+        self.flushed.borrow_mut().push_str(&String::from_utf8(pending).unwrap());
+        Ok(())
+    }
+}
+
+#[test]
+fn run_file_returns_the_final_expression_value() {
+    let path = script("final", "var x = 1 + 2;\nx * 2;");
+    match Interpreter::new().run_file(&path) {
+        Ok(MskValue::Int(6)) => {}
+        other => panic!("expected 6, got {:?}", other.map(|v| v.to_string())),
+    }
+}
+
+#[test]
+fn run_file_returns_top_level_return_value() {
+    let path = script("return", "return \"early\";\nprint \"unreachable\";");
+    let value = Interpreter::new().run_file(&path).unwrap();
+    assert_eq!(value.to_string(), "early");
+}
+
+#[test]
+fn run_file_reports_parse_errors_with_line_and_column() {
+    let path = script("parse-error", "var ok = 1;\nvar = 2;\nprint (;");
+    let diagnostics = Interpreter::new().run_file(&path).unwrap_err();
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0].stage, Stage::Parse);
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (Some(2), Some(5)));
+    assert_eq!(diagnostics[0].message, "[line 2] Error at '=': Expect variable name.");
+    assert_eq!((diagnostics[1].line, diagnostics[1].column), (Some(3), Some(8)));
+}
+
+#[test]
+fn run_file_reports_scan_errors_with_column() {
+    let path = script("scan-error", "var a = 1;\n  var b = @;");
+    let diagnostics = Interpreter::new().run_file(&path).unwrap_err();
+    assert_eq!(diagnostics[0].stage, Stage::Scan);
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (Some(2), Some(11)));
+}
+
+#[test]
+fn run_file_reports_runtime_errors() {
+    let path = script("runtime-error", "print 1;\nprint missing;");
+    let diagnostics = Interpreter::with_output(Vec::new()).run_file(&path).unwrap_err();
+    assert_eq!(diagnostics[0].stage, Stage::Runtime);
+    assert_eq!(diagnostics[0].line, Some(2));
+    assert_eq!(diagnostics[0].message, "[line 2] Undefined variable 'missing'.");
+}
+
+#[test]
+fn run_source_returns_the_final_expression_value() {
+    assert!(matches!(run_source("var x = 1+2; x;"), Ok(MskValue::Int(3))));
+    assert!(matches!(run_source("var x = 1;"), Ok(MskValue::Nil)));
+}
+
+#[test]
+fn run_source_joins_parse_errors() {
+    let err = run_source("var = 1;\nprint (;").unwrap_err().to_string();
+    assert_eq!(err.lines().count(), 2, "{}", err);
+}
+
+#[test]
+fn registered_closures_are_callable_from_scripts() {
+    let calls = Rc::new(Cell::new(0));
+    let counter = calls.clone();
+    let (mut interpreter, out) = interpreter();
+    interpreter
+        .register("twice", Rc::new(HostFn(1, |args: Vec<MskValue>| match &args[0] {
+            MskValue::Int(n) => Ok(MskValue::Int(n * 2)),
+            _ => Err("twice() expects an integer.".to_string().into()),
+        })))
+        .register("tick", Rc::new(HostFn(0, move |_| {
+            counter.set(counter.get() + 1);
+            Ok(MskValue::Nil)
+        })));
+    exec(&mut interpreter, "print twice(21); tick(); tick();").unwrap();
+    assert_eq!(out.contents(), "42\n");
+    assert_eq!(calls.get(), 2);
+    assert_eq!(exec(&mut interpreter, "twice(\"x\");").unwrap_err(), "twice() expects an integer.");
+}
+
+#[test]
+fn registered_functions_are_arity_checked() {
+    let (mut interpreter, _) = interpreter();
+    interpreter.register("tick", Rc::new(HostFn(0, |_| Ok(MskValue::Nil))));
+    assert_eq!(exec(&mut interpreter, "tick(1);").unwrap_err(), "[line 1] Expected 0 arguments but got 1.");
+}
+
+#[test]
+fn with_output_captures_every_print() {
+    let out = SharedBuffer::default();
+    let mut interpreter = Interpreter::with_output(out.clone());
+    exec(&mut interpreter, "fun show(x) { print x; } print \"a\"; show(1); for (var i = 0; i < 2; i = i + 1) print i;").unwrap();
+    assert_eq!(out.contents(), "a\n1\n0\n1\n");
+}
+
+#[test]
+fn with_output_keeps_output_written_before_an_error() {
+    let out = SharedBuffer::default();
+    let mut interpreter = Interpreter::with_output(out.clone());
+    assert!(exec(&mut interpreter, "print 1; print missing; print 2;").is_err());
+    assert_eq!(out.contents(), "1\n");
+}
+
+#[test]
+fn interpret_resolves_local_variables_itself() {
+    let (tokens, _) = Scanner::new("{ var a = 1; print a; } fun f(x) { return x * 2; } print f(3);").scan_tokens_with_diagnostics();
+    let stmts = Parser::new(tokens).parse_with_diagnostics().unwrap();
+    let out = SharedBuffer::default();
+    Interpreter::with_output(out.clone()).interpret(&stmts).unwrap();
+    assert_eq!(out.contents(), "1\n6\n");
+}
+
+#[test]
+fn interpret_reports_resolve_errors() {
+    let (tokens, _) = Scanner::new("{ var a = a; }").scan_tokens_with_diagnostics();
+    let stmts = Parser::new(tokens).parse_with_diagnostics().unwrap();
+    let err = Interpreter::new().interpret(&stmts).unwrap_err();
+    assert_eq!(err.to_string(), "[line 1] Error at 'a': Can't read local variable in its own initializer.");
+}
+
+#[test]
+fn autoflush_flushes_after_every_print() {
+    let out = FlushTracker::default();
+    let mut interpreter = Interpreter::with_output(out.clone());
+    interpreter.set_autoflush(true);
+    exec(&mut interpreter, "print 1;").unwrap();
+    assert_eq!(*out.flushed.borrow(), "1\n");
+    exec(&mut interpreter, "print \"two\";").unwrap();
+    assert_eq!(*out.flushed.borrow(), "1\ntwo\n");
+}
+
+#[test]
+fn without_autoflush_print_output_stays_buffered() {
+    let out = FlushTracker::default();
+    let mut interpreter = Interpreter::with_output(out.clone());
+    interpreter.set_autoflush(false);
+    exec(&mut interpreter, "print 1;").unwrap();
+    assert_eq!(*out.flushed.borrow(), "");
+    assert_eq!(*out.pending.borrow(), b"1\n");
+}
+
+#[test]
+fn buffered_output_is_flushed_before_a_runtime_error_is_returned() {
+    let out = FlushTracker::default();
+    let mut interpreter = Interpreter::with_output(out.clone());
+    interpreter.set_autoflush(false);
+    assert!(exec(&mut interpreter, "print \"before\"; print missing;").is_err());
+    assert_eq!(*out.flushed.borrow(), "before\n");
+
+    let out = FlushTracker::default();
+    let mut interpreter = Interpreter::with_output(out.clone());
+    interpreter.set_autoflush(false);
+    let (tokens, _) = Scanner::new("print 1;\n1 / 0;").scan_tokens_with_diagnostics();
+    let stmts = Parser::new(tokens).parse_with_diagnostics().unwrap();
+    assert!(interpreter.run_program(&stmts).is_err());
+    assert_eq!(*out.flushed.borrow(), "1\n");
+}
+
+/// 写入时记下 `print` 输出目标中已经刷新的内容，用来检查两路输出的先后顺序。
+struct OrderedErr {
+    out: FlushTracker,
+    seen: Rc<RefCell<Vec<String>>>,
+}
+
+impl Write for OrderedErr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        // `writeln!` 会把换行符单独写入一次
+        if !text.trim().is_empty() {
+            self.seen.borrow_mut().push(format!("{}| {}", self.out.flushed.borrow().trim_end(), text));
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn buffered_output_is_flushed_before_writing_to_the_error_output() {
+    let out = FlushTracker::default();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let mut interpreter = Interpreter::with_output(out.clone());
+    interpreter.set_autoflush(false);
+    interpreter.set_error_output(OrderedErr { out: out.clone(), seen: seen.clone() });
+    exec(&mut interpreter, "print \"a\"; eprint(\"b\"); print \"c\";").unwrap();
+    assert_eq!(*seen.borrow(), vec!["a| b".to_string()]);
+    assert_eq!(*out.pending.borrow(), b"c\n");
+    interpreter.flush().unwrap();
+    assert_eq!(*out.flushed.borrow(), "a\nc\n");
+}
+
+/// 直接用给定的实参调用脚本传入的函数，不经过解释器的参数个数检查。
+struct CallDirectly(Vec<MskValue>);
+
+impl Callable for CallDirectly {
+    fn arity(&self) -> usize {
+        1
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        match &args[0] {
+            MskValue::Callable(f) => f.call(interpreter, self.0.clone()),
+            other => Err(format!("expected a function but got {}", other).into()),
+        }
+    }
+}
+
+#[test]
+fn host_calls_with_the_wrong_argument_count_are_errors() {
+    let (mut interpreter, _) = interpreter();
+    interpreter.register("call_empty", Rc::new(CallDirectly(Vec::new())));
+    interpreter.register("call_three", Rc::new(CallDirectly(vec![MskValue::Int(1), MskValue::Int(2), MskValue::Int(3)])));
+    assert_eq!(
+        exec(&mut interpreter, "\ncall_empty(fun (a, b) { return a + b; });").unwrap_err(),
+        "[line 2] Expected 2 arguments but got 0."
+    );
+    assert_eq!(
+        exec(&mut interpreter, "fun add(a, b) { return a + b; }\ncall_three(add);").unwrap_err(),
+        "[line 2] Expected 2 arguments but got 3."
+    );
+}
+
+#[test]
+fn host_calls_fill_rest_parameters() {
+    let (mut interpreter, out) = interpreter();
+    interpreter.register("call_three", Rc::new(CallDirectly(vec![MskValue::Int(1), MskValue::Int(2), MskValue::Int(3)])));
+    exec(&mut interpreter, "print call_three(fun (a, ...more) { return more; });").unwrap();
+    assert_eq!(out.contents(), "[2, 3]\n");
+}
+
+/// 把单个表达式解析成 AST，解析失败时测试失败。
+fn parse_expr(src: &str) -> codecrafters_interpreter::ast::Expr {
+    let (tokens, _) = Scanner::new(src).scan_tokens_with_diagnostics();
+    let (expr, had_error) = Parser::new(tokens).parse_expr();
+    assert!(!had_error, "parse error in {:?}", src);
+    expr.unwrap()
+}
+
+#[test]
+fn eval_expr_resolves_lambda_locals() {
+    let mut interpreter = Interpreter::new();
+    let value = interpreter.eval_expr(&parse_expr("(fun (x) { return x + 1; })(5)")).unwrap();
+    assert_eq!(value.to_string(), "6");
+}
+
+#[test]
+fn eval_expr_reports_resolve_errors() {
+    let mut interpreter = Interpreter::new();
+    let err = interpreter.eval_expr(&parse_expr("this")).unwrap_err();
+    assert_eq!(err.to_string(), "[line 1] Error at 'this': Can't use 'this' outside of a class.");
+}
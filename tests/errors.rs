@@ -0,0 +1,141 @@
+use codecrafters_interpreter::{run_source, RuntimeError};
+
+#[test]
+fn undefined_variable_is_a_name_error() {
+    match run_source("var a = 1;\nprint b;") {
+        Err(RuntimeError::NameError { line, name }) => {
+            assert_eq!(line, 2);
+            assert_eq!(name, "b");
+        }
+        other => panic!("expected NameError, got {:?}", other),
+    }
+}
+
+#[test]
+fn bad_operand_is_a_type_error() {
+    assert!(matches!(run_source("1 - \"x\";"), Err(RuntimeError::TypeError { line: 1, .. })));
+}
+
+#[test]
+fn out_of_bounds_index_is_an_index_error() {
+    assert!(matches!(run_source("var a = [1];\na[5];"), Err(RuntimeError::IndexError { line: 2, index: 5 })));
+}
+
+#[test]
+fn wrong_argument_count_is_an_arity_error() {
+    match run_source("fun f(a, b) {}\nf(1);") {
+        Err(RuntimeError::ArityError { line, name, min, max, got }) => {
+            assert_eq!((line, name.as_str(), min, max, got), (2, "f", 2, Some(2), 1));
+        }
+        other => panic!("expected ArityError, got {:?}", other),
+    }
+}
+
+#[test]
+fn structured_errors_display_like_the_old_messages() {
+    assert_eq!(run_source("print b;").unwrap_err().to_string(), "[line 1] Undefined variable 'b'.");
+    assert_eq!(run_source("[1][3];").unwrap_err().to_string(), "[line 1] Index 3 out of bounds.");
+}
+
+#[test]
+fn operand_errors_show_values_and_types() {
+    match run_source("var s = \"x\";\ns - 1;") {
+        Err(e @ RuntimeError::TypeError { .. }) => {
+            assert_eq!(e.to_string(), "[line 2] '-' expected numbers but got \"x\" (string) and 1 (number).");
+        }
+        other => panic!("expected TypeError, got {:?}", other),
+    }
+}
+
+#[test]
+fn operand_errors_describe_nil_and_lists() {
+    let message = run_source("nil * [1, 2];").unwrap_err().to_string();
+    assert_eq!(message, "[line 1] '*' expected numbers but got nil (nil) and [1, 2] (list).");
+}
+
+#[test]
+fn optional_argument_native_reports_its_range() {
+    assert_eq!(
+        run_source("slice();").unwrap_err().to_string(),
+        "[line 1] Function 'slice' expected between 2 and 3 arguments but got 0."
+    );
+    assert_eq!(
+        run_source("\nslice([1], 0, 1, 2);").unwrap_err().to_string(),
+        "[line 2] Function 'slice' expected between 2 and 3 arguments but got 4."
+    );
+}
+
+#[test]
+fn rest_parameter_function_reports_its_minimum() {
+    match run_source("fun f(a, ...rest) {}\nf();") {
+        Err(e @ RuntimeError::ArityError { .. }) => {
+            assert_eq!(e.to_string(), "[line 2] Function 'f' expected at least 1 arguments but got 0.");
+        }
+        other => panic!("expected ArityError, got {:?}", other),
+    }
+}
+
+#[test]
+fn fixed_arity_function_keeps_the_short_message() {
+    assert_eq!(run_source("fun f(a) {}\nf(1, 2);").unwrap_err().to_string(), "[line 2] Expected 1 arguments but got 2.");
+}
+
+/// 在与进程主线程同样大小（8MB）的栈上运行源码。测试线程默认的栈更小，不能代表实际使用场景。
+fn run_source_on_main_sized_stack(src: impl Into<String>) -> Result<String, String> {
+    let src = src.into();
+    std::thread::Builder::new()
+        .stack_size(8 * 1024 * 1024)
+        .spawn(move || run_source(&src).map(|v| v.to_string()).map_err(|e| e.to_string()))
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
+#[test]
+fn infinite_recursion_is_reported_before_the_native_stack_overflows() {
+    assert_eq!(
+        run_source_on_main_sized_stack("fun f(n) { return f(n + 1); } f(0);"),
+        Err("Stack overflow: maximum call depth exceeded.".to_string())
+    );
+}
+
+#[test]
+fn recursion_through_loops_and_blocks_fits_the_default_depth() {
+    let src = "fun f(n) {
+            for (var i = 0; i < 1; i = i + 1) {
+                if (true) { while (true) { return g(n); } }
+            }
+        }
+        fun g(n) { if (n == 0) return 0; { return 1 + f(n - 1); } }
+        f(95);";
+    assert_eq!(run_source_on_main_sized_stack(src), Ok("95".to_string()));
+}
+
+#[test]
+fn run_source_uses_the_documented_default_depth() {
+    assert_eq!(codecrafters_interpreter::interpreter::DEFAULT_MAX_CALL_DEPTH, 200);
+    let depth = "fun depth(n) { if (n == 0) return 0; return 1 + depth(n - 1); }";
+    assert_eq!(run_source_on_main_sized_stack(format!("{} depth(199);", depth)), Ok("199".to_string()));
+    assert_eq!(
+        run_source_on_main_sized_stack(format!("{} depth(250);", depth)),
+        Err("Stack overflow: maximum call depth exceeded.".to_string())
+    );
+}
+
+#[test]
+fn a_larger_stack_allows_the_cli_depth() {
+    let result = std::thread::Builder::new()
+        .stack_size(32 * 1024 * 1024)
+        .spawn(|| {
+            let mut interpreter = codecrafters_interpreter::Interpreter::new();
+            interpreter.set_max_call_depth(1000);
+            let src = "fun depth(n) { if (n == 0) return 0; return 1 + depth(n - 1); } depth(999);";
+            let (tokens, _) = codecrafters_interpreter::Scanner::new(src).scan_tokens_with_diagnostics();
+            let stmts = codecrafters_interpreter::Parser::new(tokens).parse_with_diagnostics().unwrap();
+            interpreter.run_program(&stmts).map(|v| v.to_string()).map_err(|e| e.to_string())
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    assert_eq!(result, Ok("999".to_string()));
+}
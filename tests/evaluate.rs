@@ -0,0 +1,338 @@
+mod common;
+
+use common::{exec, interpreter, run, run_err};
+
+#[test]
+fn parallel_assignment_swaps_variables() {
+    assert_eq!(run("var a = 1; var b = 2; (a, b) = (b, a); print a; print b;"), "2\n1\n");
+}
+
+#[test]
+fn parallel_assignment_rejects_length_mismatch() {
+    assert_eq!(
+        run_err("var a; var b;\n(a, b) = (1, 2, 3);"),
+        "[line 2] Expected 2 values in parallel assignment but got 3."
+    );
+}
+
+#[test]
+fn min_assign_lowers_larger_value_and_keeps_smaller() {
+    assert_eq!(run("var x = 10; x min= 5; print x; x min= 7; print x;"), "5\n5\n");
+}
+
+#[test]
+fn max_assign_raises_smaller_value() {
+    assert_eq!(run("var x = 1; x max= 0; print x; x max= 3; print x;"), "1\n3\n");
+}
+
+#[test]
+fn min_max_assign_updates_list_elements() {
+    assert_eq!(run("var a = [1, 8]; a[0] max= 4; a[1] min= 3; print a;"), "[4, 3]\n");
+}
+
+#[test]
+fn min_max_assign_evaluates_index_target_once() {
+    let src = "var calls = 0;
+        fun at() { calls = calls + 1; return 0; }
+        var a = [1];
+        a[at()] max= 5;
+        print a; print calls;";
+    assert_eq!(run(src), "[5]\n1\n");
+}
+
+#[test]
+fn min_max_assign_ignores_shadowed_natives() {
+    assert_eq!(run("fun min(a, b) { return 99; } var x = 10; x min= 2; print x;"), "2\n");
+}
+
+#[test]
+fn min_max_assign_requires_numbers() {
+    assert_eq!(
+        run_err("var x = 2;\nx min= \"s\";"),
+        "[line 2] 'min' expected numbers but got 2 (number) and \"s\" (string)."
+    );
+}
+
+#[test]
+fn negative_zero_equals_positive_zero() {
+    assert_eq!(run("print 0.0 == -0.0; print 0.0 != -0.0; print 0 == -0.0;"), "true\nfalse\ntrue\n");
+}
+
+#[test]
+fn nan_is_never_equal_to_itself() {
+    assert_eq!(run("var n = 1e999 - 1e999; print n == n; print n != n;"), "false\ntrue\n");
+}
+
+#[test]
+fn nan_stays_unequal_with_float_tolerance() {
+    let (mut interpreter, out) = interpreter();
+    interpreter.set_float_tolerance(1e-9);
+    exec(&mut interpreter, "var n = 1e999 - 1e999; print n == n; print n != n; print 0.0 == -0.0;").unwrap();
+    assert_eq!(out.contents(), "false\ntrue\ntrue\n");
+}
+
+#[test]
+fn or_short_circuits_around_and() {
+    assert_eq!(run("print true or false and false; print nil or \"b\" and \"c\";"), "true\nc\n");
+}
+
+#[test]
+fn booleans_are_ordered_false_before_true() {
+    assert_eq!(
+        run("print false < true; print true <= true; print true > false; print false >= true;"),
+        "true\ntrue\ntrue\nfalse\n"
+    );
+}
+
+#[test]
+fn comparing_boolean_with_number_is_an_error() {
+    assert_eq!(
+        run_err("print true < 1;"),
+        "[line 1] '<' expected two numbers or two booleans but got true (boolean) and 1 (number)."
+    );
+}
+
+#[test]
+fn chained_assignment_sets_every_target() {
+    assert_eq!(run("var a; var b; var c; a = b = c = 5; print a; print b; print c;"), "5\n5\n5\n");
+}
+
+#[test]
+fn chained_assignment_evaluates_the_value_once() {
+    let src = "var calls = 0; fun next() { calls = calls + 1; return calls; }
+        var a; var b = [0]; var o = 0;
+        class O {} o = O();
+        a = b[0] = o.f = next();
+        print a; print b[0]; print o.f; print calls;";
+    assert_eq!(run(src), "1\n1\n1\n1\n");
+}
+
+#[test]
+fn chained_assignment_rejects_unassignable_targets() {
+    assert_eq!(run_err("var a; a = 1 = 2;"), "[line 1] Error at '=': Invalid assignment target.");
+}
+
+#[test]
+fn in_tests_list_membership_with_equality() {
+    assert_eq!(
+        run("print 2 in [1, 2, 3]; print 4 in [1, 2, 3]; print 1.0 in [1]; print nil in [nil]; print 1 in [];"),
+        "true\nfalse\ntrue\ntrue\nfalse\n"
+    );
+}
+
+#[test]
+fn in_tests_substrings() {
+    assert_eq!(run("print \"a\" in \"cat\"; print \"dog\" in \"cat\";"), "true\nfalse\n");
+}
+
+#[test]
+fn in_expression_works_inside_for_in() {
+    assert_eq!(run("for (var x in [1, 2, 3]) print x in [2, 3];"), "false\ntrue\ntrue\n");
+}
+
+#[test]
+fn in_rejects_other_collections() {
+    assert_eq!(
+        run_err("print 1 in 2;"),
+        "[line 1] 'in' expected a string or a list on the right but got 1 (number) and 2 (number)."
+    );
+}
+
+#[test]
+fn equality_chain_compares_the_boolean_result() {
+    assert_eq!(run("print 1 == 1 == true; print 1 == 2 == false; print 1 == 1 == 1;"), "true\ntrue\nfalse\n");
+}
+
+#[test]
+fn negative_index_assignment_targets_from_the_end() {
+    assert_eq!(run("var arr = [1, 2, 3]; arr[-1] = 9; arr[-3] = 7; print arr; print arr[-2];"), "[7, 2, 9]\n2\n");
+}
+
+#[test]
+fn negative_index_assignment_out_of_range_is_an_error() {
+    assert_eq!(run_err("var arr = [1, 2, 3];\narr[-100] = 1;"), "[line 2] Index -100 out of bounds.");
+    assert_eq!(run_err("var arr = [1, 2, 3];\narr[-4] = 1;"), "[line 2] Index -4 out of bounds.");
+}
+
+#[test]
+fn index_assignment_requires_an_integer() {
+    assert_eq!(run_err("var arr = [1];\narr[0.5] = 1;"), "[line 2] List index must be an integer but got 0.5 (number).");
+}
+
+#[test]
+fn negative_index_works_with_min_max_assign() {
+    assert_eq!(run("var arr = [1, 8]; arr[-1] min= 3; print arr;"), "[1, 3]\n");
+}
+
+#[test]
+fn nil_equals_only_nil() {
+    assert_eq!(
+        run("print nil == nil; print nil != nil; print nil == false; print nil != 0; print nil == \"\";"),
+        "true\nfalse\nfalse\ntrue\nfalse\n"
+    );
+}
+
+#[test]
+fn modulo_computes_remainders() {
+    assert_eq!(run("print 10 % 3; print 7.5 % 2; print -7 % 3;"), "1\n1.5\n-1\n");
+}
+
+#[test]
+fn modulo_by_zero_is_an_error() {
+    assert_eq!(run_err("print 1 % 0;"), "[line 1] Modulo by zero is not allowed.");
+}
+
+#[test]
+fn modulo_shares_precedence_with_multiplication() {
+    assert_eq!(run("print 2 + 10 % 4 * 3;"), "8\n");
+}
+
+#[test]
+fn integer_arithmetic_stays_integral() {
+    assert_eq!(run("print 2 + 3; print 7 - 10; print 6 * 7; print type(1 + 1);"), "5\n-3\n42\nnumber\n");
+}
+
+#[test]
+fn mixed_arithmetic_promotes_to_float() {
+    assert_eq!(run("print 1 + 0.5; print 2 * 1.5; print 1 == 1.0; print 1 < 1.5;"), "1.5\n3\ntrue\ntrue\n");
+}
+
+#[test]
+fn integer_division_is_exact_or_promotes() {
+    assert_eq!(run("print 6 / 3; print 7 / 2;"), "2\n3.5\n");
+}
+
+#[test]
+fn integer_overflow_promotes_to_float() {
+    assert_eq!(run("print 9223372036854775807 * 2;"), "18446744073709552000\n");
+}
+
+#[test]
+fn string_escapes_are_decoded() {
+    assert_eq!(run(r#"print len("a\tb"); print "say \"hi\""; print "back\\slash"; print "1\n2";"#), "3\nsay \"hi\"\nback\\slash\n1\n2\n");
+}
+
+#[test]
+fn unicode_escapes_are_decoded() {
+    assert_eq!(run(r#"print "\u{48}\u{69}"; print len("\u{1F600}"); print "\u{4e2d}";"#), "Hi\n1\n中\n");
+}
+
+#[test]
+fn invalid_unicode_escapes_are_scan_errors() {
+    let sources = [
+        r#"print "\u41";"#,
+        r#"print "\u{}";"#,
+        r#"print "\u{110000}";"#,
+        r#"print "\u{d800}";"#,
+        r#"print "\u{4g}";"#,
+        r#"print "\u{41";"#,
+    ];
+    for src in sources {
+        assert_eq!(run_err(src), "[line 1] Error: Invalid unicode escape sequence.", "{}", src);
+    }
+}
+
+#[test]
+fn unknown_escape_is_a_scan_error() {
+    assert_eq!(run_err("var s = 1;\nprint \"\\q\";"), "[line 2] Error: Unknown escape sequence '\\q'.");
+}
+
+#[test]
+fn ternary_evaluates_only_the_chosen_branch() {
+    let src = "fun boom() { print \"evaluated\"; return 0; }
+        print true ? \"yes\" : boom(); print nil ? boom() : \"no\";";
+    assert_eq!(run(src), "yes\nno\n");
+}
+
+#[test]
+fn lists_are_built_read_and_written() {
+    assert_eq!(
+        run("var a = [1, \"two\", [3]]; print a; print a[1]; a[0] = 5; print a[0]; print a[2][0];"),
+        "[1, two, [3]]\ntwo\n5\n3\n"
+    );
+}
+
+#[test]
+fn list_index_errors_report_the_line() {
+    assert_eq!(run_err("var a = [1];\nprint a[1];"), "[line 2] Index 1 out of bounds.");
+    assert_eq!(run_err("var a = [1];\nprint a[\"0\"];"), "[line 2] List index must be an integer but got \"0\" (string).");
+}
+
+#[test]
+fn lists_compare_by_reference() {
+    assert_eq!(
+        run("var a = [1]; var b = a; print a == a; print a == b; print a != b; print a == [1]; print a != [1];"),
+        "true\ntrue\nfalse\nfalse\ntrue\n"
+    );
+}
+
+#[test]
+fn functions_and_builders_compare_by_reference() {
+    assert_eq!(
+        run("fun f() {} var g = f; var sb = sb_new(); print f == g; print f == clock; print sb == sb; print sb == sb_new();"),
+        "true\nfalse\ntrue\nfalse\n"
+    );
+}
+
+#[test]
+fn self_referencing_list_can_be_compared() {
+    assert_eq!(run("var a = [0]; a[0] = a; print a == a; print a[0] == a;"), "true\ntrue\n");
+}
+
+#[test]
+fn if_expression_yields_the_chosen_branch() {
+    assert_eq!(
+        run("fun sign(n) { return if (n < 0) -1 else if (n == 0) 0 else 1; } print sign(-5); print sign(0); print sign(3);"),
+        "-1\n0\n1\n"
+    );
+}
+
+#[test]
+fn if_expression_does_not_evaluate_the_other_branch() {
+    assert_eq!(run("var x = if (true) 1 else undefined_name; print x;"), "1\n");
+}
+
+#[test]
+fn float_equality_is_exact_by_default() {
+    assert_eq!(run("print 0.1 + 0.2 == 0.3; print 0.1 + 0.2 != 0.3;"), "false\ntrue\n");
+}
+
+#[test]
+fn float_tolerance_treats_close_floats_as_equal() {
+    let (mut interpreter, out) = interpreter();
+    interpreter.set_float_tolerance(1e-9);
+    exec(&mut interpreter, "print 0.1 + 0.2 == 0.3; print 0.1 + 0.2 != 0.3; print 1.0 == 1.1;").unwrap();
+    assert_eq!(out.contents(), "true\nfalse\nfalse\n");
+}
+
+#[test]
+fn float_tolerance_does_not_apply_to_integers() {
+    let (mut interpreter, out) = interpreter();
+    interpreter.set_float_tolerance(1e-3);
+    exec(&mut interpreter, "print 100000 == 100001;").unwrap();
+    assert_eq!(out.contents(), "false\n");
+}
+
+#[test]
+fn exponent_literals_evaluate_as_numbers() {
+    assert_eq!(run("print 1e3 + 1; print 1.5E2; print 2e-2 * 100;"), "1001\n150\n2\n");
+}
+
+#[test]
+fn digit_separators_do_not_change_the_value() {
+    assert_eq!(run("print 1_000_000 + 1; print 3.141_592; print 1_0 == 10;"), "1000001\n3.141592\ntrue\n");
+}
+
+#[test]
+fn stray_parenthesized_lists_are_reported_before_running() {
+    let message = "Error at '(': Parenthesized lists can only be used in parallel assignment.";
+    assert_eq!(run_err("print \"start\";\nif (false) print (1, 2);"), format!("[line 2] {}", message));
+    assert_eq!(run_err("fun never() { return 1 + (2, 3); }"), format!("[line 1] {}", message));
+    assert_eq!(run_err("var a = 1; var b = 2;\n(a, b) = (1, (2, 3));"), format!("[line 2] {}", message));
+}
+
+#[test]
+fn spread_outside_call_arguments_does_not_parse() {
+    assert_eq!(run_err("var xs = [1];\nif (false) print [...xs];"), "[line 2] Error at '...': Expect expression.");
+    assert_eq!(run("var xs = [1, 2]; fun add(a, b) { return a + b; } print add(...xs);"), "3\n");
+}
@@ -0,0 +1,685 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+use codecrafters_interpreter::Interpreter;
+use common::{exec, interpreter, run, run_err, SharedBuffer};
+
+#[test]
+fn now_iso_formats_the_injected_clock() {
+    let (mut interpreter, out) = interpreter();
+    interpreter.set_clock(|| Duration::from_secs(1_714_566_600));
+    exec(&mut interpreter, "print now_iso();").unwrap();
+    assert_eq!(out.contents(), "2024-05-01T12:30:00Z\n");
+}
+
+#[test]
+fn now_iso_handles_leap_days() {
+    let (mut interpreter, out) = interpreter();
+    interpreter.set_clock(|| Duration::from_secs(951_868_799));
+    exec(&mut interpreter, "print now_iso();").unwrap();
+    assert_eq!(out.contents(), "2000-02-29T23:59:59Z\n");
+}
+
+#[test]
+fn argv_returns_script_arguments_in_order() {
+    let (mut interpreter, out) = interpreter();
+    interpreter.set_args(vec!["first".to_string(), "second arg".to_string()]);
+    exec(&mut interpreter, "var args = argv(); print len(args); print args[0]; print args[1];").unwrap();
+    assert_eq!(out.contents(), "2\nfirst\nsecond arg\n");
+}
+
+#[test]
+fn argv_is_empty_without_arguments() {
+    assert_eq!(run("print argv();"), "[]\n");
+}
+
+#[test]
+fn eprint_writes_to_the_error_output_only() {
+    let (mut interpreter, out) = interpreter();
+    let err = SharedBuffer::default();
+    interpreter.set_error_output(err.clone());
+    exec(&mut interpreter, "print \"data\"; eprint(\"warning\"); eprint(42);").unwrap();
+    assert_eq!(out.contents(), "data\n");
+    assert_eq!(err.contents(), "warning\n42\n");
+}
+
+#[test]
+fn memoize_caches_results_per_argument() {
+    let src = "var calls = 0;
+        fun square(n) { calls = calls + 1; return n * n; }
+        var fast = memoize(square);
+        print fast(4); print fast(4); print fast(5); print calls;";
+    assert_eq!(run(src), "16\n16\n25\n2\n");
+}
+
+#[test]
+fn memoize_keeps_the_arity_range_of_the_wrapped_function() {
+    assert_eq!(run("var m = memoize(max); print m(1, 2); print m(3, 9, 4); print m(7);"), "2\n9\n7\n");
+    let src = "var calls = 0;
+        var sum = memoize(fun (first, ...rest) { calls = calls + 1; var total = first; for (var x in rest) total = total + x; return total; });
+        print sum(1); print sum(1, 2, 3); print sum(1, 2, 3); print calls;";
+    assert_eq!(run(src), "1\n6\n6\n2\n");
+    assert_eq!(run_err("var sum = memoize(fun (first, ...rest) { return first; });\nsum();"), "[line 2] Function 'sum' expected at least 1 arguments but got 0.");
+}
+
+#[test]
+fn memoize_distinguishes_numbers_from_strings() {
+    assert_eq!(run("var t = memoize(fun (x) { return type(x); }); print t(1); print t(\"1\");"), "number\nstring\n");
+}
+
+#[test]
+fn memoize_keys_strings_unambiguously() {
+    let src = "var join = memoize(fun (a, b) { return a + \"|\" + b; });
+        print join(\"a),String(b\", \"c\"); print join(\"a\", \"b),String(c\");";
+    assert_eq!(run(src), "a),String(b|c\na|b),String(c\n");
+}
+
+#[test]
+fn memoize_distinguishes_instances_of_the_same_class() {
+    let src = "class Box { init(v) { this.v = v; } }
+        var unbox = memoize(fun (b) { return b.v; });
+        print unbox(Box(1)); print unbox(Box(2));";
+    assert_eq!(run(src), "1\n2\n");
+}
+
+#[test]
+fn memoize_keys_builders_by_identity() {
+    let src = "var first = sb_new(); var second = sb_new();
+        var stamp = memoize(fun (b) { sb_append(b, \"!\"); return b; });
+        stamp(first); stamp(second); stamp(first);
+        print sb_build(first); print sb_build(second);";
+    assert_eq!(run(src), "!\n!\n");
+}
+
+#[test]
+fn enumerate_pairs_indices_with_elements() {
+    assert_eq!(run("print enumerate([\"a\", \"b\"]);"), "[[0, a], [1, b]]\n");
+    assert_eq!(run("for (var pair in enumerate([\"x\"])) print pair[0] + 1;"), "1\n");
+}
+
+#[test]
+fn enumerate_of_empty_list_is_empty() {
+    assert_eq!(run("print enumerate([]);"), "[]\n");
+}
+
+#[test]
+fn enumerate_requires_a_list() {
+    assert_eq!(run_err("enumerate(\"ab\");"), "enumerate() expects a list but got ab (string).");
+}
+
+#[test]
+fn slice_returns_the_half_open_range() {
+    assert_eq!(run("print slice([1, 2, 3, 4], 1, 3); print slice([1, 2, 3, 4], 2);"), "[2, 3]\n[3, 4]\n");
+}
+
+#[test]
+fn slice_counts_negative_bounds_from_the_end() {
+    assert_eq!(run("print slice([1, 2, 3, 4], 0, -1); print slice([1, 2, 3, 4], -2);"), "[1, 2, 3]\n[3, 4]\n");
+}
+
+#[test]
+fn slice_clamps_out_of_range_bounds() {
+    assert_eq!(
+        run("print slice([1, 2, 3], -10, 10); print slice([1, 2, 3], 5); print slice([1, 2, 3], 2, 1);"),
+        "[1, 2, 3]\n[]\n[]\n"
+    );
+}
+
+#[test]
+fn slice_returns_an_independent_list() {
+    assert_eq!(run("var a = [1, 2, 3]; var b = slice(a, 0); b[0] = 9; print a; print b;"), "[1, 2, 3]\n[9, 2, 3]\n");
+}
+
+#[test]
+fn slice_rejects_non_whole_bounds() {
+    assert_eq!(run_err("slice([1], 0.5);"), "slice() expects a list and whole-number bounds.");
+}
+
+#[test]
+fn reverse_returns_a_new_list_and_keeps_the_original() {
+    assert_eq!(run("var a = [1, 2, 3]; var b = reverse(a); print b; print a;"), "[3, 2, 1]\n[1, 2, 3]\n");
+}
+
+#[test]
+fn reverse_mut_reverses_every_reference_in_place() {
+    assert_eq!(run("var a = [1, 2, 3]; var alias = a; print reverse_mut(a); print alias;"), "nil\n[3, 2, 1]\n");
+}
+
+#[test]
+fn reverse_handles_empty_lists() {
+    assert_eq!(run("var e = []; print reverse(e); reverse_mut(e); print e;"), "[]\n[]\n");
+}
+
+#[test]
+fn reverse_requires_a_list() {
+    assert_eq!(run_err("reverse_mut(\"ab\");"), "reverse_mut() expects a list but got ab (string).");
+}
+
+#[test]
+fn ord_and_chr_convert_code_points() {
+    assert_eq!(run("print ord(\"A\"); print chr(97);"), "65\na\n");
+}
+
+#[test]
+fn ord_and_chr_round_trip_non_ascii_characters() {
+    assert_eq!(run("var c = ord(\"😀\"); print c; print chr(c) == \"😀\";"), "128512\ntrue\n");
+}
+
+#[test]
+fn ord_requires_a_single_character() {
+    assert_eq!(run_err("ord(\"\");"), "ord() expects a string of exactly one character.");
+    assert_eq!(run_err("ord(\"ab\");"), "ord() expects a string of exactly one character.");
+}
+
+#[test]
+fn chr_rejects_invalid_code_points() {
+    assert_eq!(run_err("chr(55296);"), "chr() got an invalid code point 55296.");
+    assert_eq!(run_err("chr(-1);"), "chr() got an invalid code point -1.");
+}
+
+#[test]
+fn partition_splits_by_predicate_keeping_order() {
+    assert_eq!(
+        run("print partition([1, 2, 3, 4, 5], fun (n) { return n % 2 == 0; });"),
+        "[[2, 4], [1, 3, 5]]\n"
+    );
+}
+
+#[test]
+fn partition_of_empty_list_gives_two_empty_lists() {
+    assert_eq!(run("print partition([], fun (n) { return true; });"), "[[], []]\n");
+}
+
+#[test]
+fn partition_propagates_predicate_errors() {
+    assert_eq!(
+        run_err("partition([1, \"x\"], fun (n) {\n  return n > 0;\n});"),
+        "[line 2] '>' expected two numbers or two booleans but got \"x\" (string) and 0 (number)."
+    );
+}
+
+#[test]
+fn pretty_print_indents_nested_lists() {
+    assert_eq!(
+        run("pretty_print([1, [\"a\", []], [[true]], nil]);"),
+        "[\n  1,\n  [\n    a,\n    []\n  ],\n  [\n    [\n      true\n    ]\n  ],\n  nil\n]\n"
+    );
+}
+
+#[test]
+fn pretty_print_writes_scalars_like_print() {
+    assert_eq!(run("pretty_print(1.5); pretty_print(\"s\"); pretty_print([]);"), "1.5\ns\n[]\n");
+}
+
+#[test]
+fn pretty_print_stops_at_cycles() {
+    assert_eq!(run("var a = [1]; a[0] = a; var b = [a, a]; pretty_print(b);"), "[\n  [\n    [...]\n  ],\n  [\n    [...]\n  ]\n]\n");
+}
+
+#[test]
+fn repr_quotes_and_escapes_strings() {
+    assert_eq!(
+        run(r#"print repr("say \"hi\"\n\tback\\slash\0"); print repr("\u{7}\u{85}é");"#),
+        "\"say \\\"hi\\\"\\n\\tback\\\\slash\\0\"\n\"\\u{7}\\u{85}é\"\n"
+    );
+}
+
+#[test]
+fn repr_writes_lists_and_scalars_in_source_form() {
+    assert_eq!(run(r#"print repr([1, "a", [nil, true]]); print repr(2.5);"#), "[1, \"a\", [nil, true]]\n2.5\n");
+}
+
+#[test]
+fn repr_stops_at_cycles() {
+    assert_eq!(run("var a = [1]; a[0] = a; print repr(a);"), "[[...]]\n");
+}
+
+/// 用固定种子的 xorshift 生成包含控制字符、引号和反斜杠的字符串，
+/// 检查 `repr` 的结果作为字符串字面量重新执行后与原字符串相等。
+#[test]
+fn repr_round_trips_generated_strings() {
+    const POOL: &[char] = &[
+        'a', 'Z', ' ', '"', '\\', '\n', '\t', '\r', '\0', '\u{1}', '\u{1b}', '\u{7f}', '\u{85}', '{', '}', 'é', '中',
+        '😀',
+    ];
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    for _ in 0..200 {
+        let len = (next() % 12) as usize;
+        let original: String = (0..len).map(|_| POOL[(next() % POOL.len() as u64) as usize]).collect();
+        // 原字符串只用 `\u{..}` 写出，不依赖 repr 自己的转义规则
+        let literal: String = original.chars().map(|c| format!("\\u{{{:x}}}", c as u32)).collect();
+        let (mut first, out) = interpreter();
+        exec(&mut first, &format!("print repr(\"{}\");", literal)).unwrap();
+        let repr = out.contents().strip_suffix('\n').unwrap().to_string();
+        let (mut second, out) = interpreter();
+        exec(&mut second, &format!("print {} == \"{}\";", repr, literal)).unwrap();
+        assert_eq!(out.contents(), "true\n", "{:?} -> {}", original, repr);
+    }
+}
+
+#[test]
+fn get_returns_the_element_when_in_range() {
+    assert_eq!(run("var a = [10, 20, 30]; print get(a, 1); print get(a, -1, 0);"), "20\n30\n");
+}
+
+#[test]
+fn get_returns_the_default_when_out_of_range() {
+    assert_eq!(run("var a = [10]; print get(a, 5, \"none\"); print get(a, -2, 0); print get(a, 1);"), "none\n0\nnil\n");
+}
+
+#[test]
+fn get_requires_a_whole_number_index() {
+    assert_eq!(run_err("get([1], 0.5);"), "get() expects a list and a whole-number index.");
+}
+
+#[test]
+fn string_builder_accumulates_many_fragments() {
+    let src = "var sb = sb_new();
+        for (var i = 0; i < 1000; i = i + 1) sb_append(sb, str(i % 10));
+        var s = sb_build(sb);
+        print len(s); print slice(to_array(s), 0, 12);";
+    assert_eq!(run(src), "1000\n[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1]\n");
+}
+
+#[test]
+fn string_builder_matches_plus_concatenation() {
+    let src = "var sb = sb_new(); var plus = \"\";
+        for (var i = 0; i < 1000; i = i + 1) { sb_append(sb, \"ab\"); plus = plus + \"ab\"; }
+        print sb_build(sb) == plus;";
+    assert_eq!(run(src), "true\n");
+}
+
+#[test]
+fn env_reads_injected_variables() {
+    let (mut interpreter, out) = interpreter();
+    interpreter.set_env_vars(HashMap::from([("MSK_MODE".to_string(), "test".to_string())]));
+    exec(&mut interpreter, "print env(\"MSK_MODE\"); print env(\"MSK_MODE\", \"prod\");").unwrap();
+    assert_eq!(out.contents(), "test\ntest\n");
+}
+
+#[test]
+fn env_returns_nil_or_default_for_missing_variables() {
+    let (mut interpreter, out) = interpreter();
+    interpreter.set_env_vars(HashMap::new());
+    exec(&mut interpreter, "print env(\"PATH\"); print env(\"PATH\", \"fallback\");").unwrap();
+    assert_eq!(out.contents(), "nil\nfallback\n");
+}
+
+#[test]
+fn floor_mod_follows_the_sign_of_the_divisor() {
+    assert_eq!(
+        run("print floor_mod(-7, 3); print -7 % 3; print floor_mod(7, -3); print floor_mod(7.5, 2);"),
+        "2\n-1\n-2\n1.5\n"
+    );
+}
+
+#[test]
+fn floor_mod_by_zero_is_an_error() {
+    assert_eq!(run_err("floor_mod(1, 0);"), "floor_mod() divisor must not be zero.");
+}
+
+#[test]
+fn is_nan_and_is_finite_classify_numbers() {
+    assert_eq!(
+        run("var nan = 1e999 - 1e999; print is_nan(nan); print is_nan(1); print is_finite(1e308 * 10); print is_finite(2.5); print is_finite(nan);"),
+        "true\nfalse\nfalse\ntrue\nfalse\n"
+    );
+}
+
+#[test]
+fn numeric_predicates_reject_non_numbers() {
+    assert_eq!(run_err("is_nan(\"x\");"), "is_nan() expects a number.");
+    assert_eq!(run_err("is_finite(nil);"), "is_finite() expects a number.");
+}
+
+#[test]
+fn sleep_passes_the_duration_to_the_sleeper() {
+    let (mut interpreter, out) = interpreter();
+    let slept = Rc::new(RefCell::new(Vec::new()));
+    let record = slept.clone();
+    interpreter.set_sleeper(move |duration| record.borrow_mut().push(duration));
+    exec(&mut interpreter, "print sleep(250); sleep(1.5); sleep(0);").unwrap();
+    assert_eq!(out.contents(), "nil\n");
+    assert_eq!(*slept.borrow(), vec![Duration::from_millis(250), Duration::from_micros(1500), Duration::ZERO]);
+}
+
+#[test]
+fn sleep_rejects_negative_durations() {
+    assert_eq!(run_err("sleep(-1);"), "sleep() expects a non-negative duration but got -1.");
+    assert_eq!(run_err("sleep(\"1\");"), "sleep() expects a number of milliseconds.");
+}
+
+#[test]
+fn sleep_rejects_durations_too_large_for_the_clock() {
+    assert_eq!(run_err("sleep(1e30);"), "sleep() duration is too large.");
+}
+
+#[test]
+fn to_hex_and_to_bin_format_integers() {
+    assert_eq!(run("print to_hex(255); print to_bin(5); print to_hex(0); print to_hex(16.0);"), "0xff\n0b101\n0x0\n0x10\n");
+}
+
+#[test]
+fn to_hex_and_to_bin_keep_the_sign_outside_the_prefix() {
+    assert_eq!(run("print to_hex(-255); print to_bin(-2);"), "-0xff\n-0b10\n");
+}
+
+#[test]
+fn to_hex_rejects_fractions() {
+    assert_eq!(run_err("to_hex(1.5);"), "to_hex() expects an integer.");
+}
+
+#[test]
+fn len_counts_characters_and_elements() {
+    assert_eq!(run("print len(\"hello\"); print len(\"héllo\"); print len([1, [2, 3]]); print len(\"\");"), "5\n5\n2\n0\n");
+}
+
+#[test]
+fn len_rejects_other_values() {
+    assert_eq!(run_err("len(5);"), "len() expects a string or list.");
+    assert_eq!(run_err("len(nil);"), "len() expects a string or list.");
+}
+
+#[test]
+fn assert_approx_passes_within_epsilon() {
+    assert_eq!(run("print assert_approx(0.1 + 0.2, 0.3); assert_approx(1, 1.05, 0.1); print \"ok\";"), "nil\nok\n");
+}
+
+#[test]
+fn assert_approx_reports_the_difference() {
+    assert_eq!(run_err("assert_approx(1, 2);"), "assert_approx() failed: 1 and 2 differ by 1 (epsilon 0.000000001).");
+}
+
+#[test]
+fn assert_approx_rejects_bad_arguments() {
+    assert_eq!(run_err("assert_approx(\"1\", 1);"), "assert_approx() expects two numbers.");
+    assert_eq!(run_err("assert_approx(1, 1, -1);"), "assert_approx() expects a non-negative epsilon.");
+}
+
+#[test]
+fn type_names_each_kind_of_value() {
+    assert_eq!(
+        run("print type(1); print type(1.5); print type(\"s\"); print type(true); print type(nil); print type(clock); print type([1]);"),
+        "number\nnumber\nstring\nboolean\nnil\nfunction\nlist\n"
+    );
+}
+
+#[test]
+fn type_supports_dynamic_dispatch() {
+    assert_eq!(run("fun f(x) { if (type(x) == \"string\") return \"s\"; return \"other\"; } print f(\"a\"); print f(1);"), "s\nother\n");
+}
+
+#[test]
+fn num_and_str_convert_between_strings_and_numbers() {
+    assert_eq!(run("print num(\"3.5\") + 1; print num(\" 7 \") * 2; print str(42) + \"!\"; print str(nil);"), "4.5\n14\n42!\nnil\n");
+}
+
+#[test]
+fn num_rejects_unparseable_input() {
+    assert_eq!(run_err("num(\"abc\");"), "num() could not parse \"abc\" as a number.");
+    assert_eq!(run_err("num(\"inf\");"), "num() could not parse \"inf\" as a number.");
+    assert_eq!(run_err("num(\" NaN\");"), "num() could not parse \"NaN\" as a number.");
+    assert_eq!(run_err("num(\"-infinity\");"), "num() could not parse \"-infinity\" as a number.");
+    assert_eq!(run_err("num(\"1e999\");"), "num() could not parse \"1e999\" as a number.");
+    assert_eq!(run_err("num(1);"), "num() expects a string.");
+}
+
+#[test]
+fn compose_applies_right_to_left_and_pipe_left_to_right() {
+    assert_eq!(
+        run("fun inc(x) { return x + 1; } fun dbl(x) { return x * 2; } print compose(inc, dbl)(5); print pipe(inc, dbl)(5);"),
+        "11\n12\n"
+    );
+}
+
+#[test]
+fn compose_rejects_non_unary_functions() {
+    assert_eq!(run_err("fun add(a, b) { return a + b; } compose(add, add);"), "compose() expects two functions that take one argument.");
+    assert_eq!(run_err("pipe(1, 2);"), "pipe() expects two functions that take one argument.");
+}
+
+#[test]
+fn math_natives_compute_expected_values() {
+    assert_eq!(
+        run("print sqrt(16); print abs(-3); print abs(-2.5); print floor(2.7); print ceil(2.1); print round(2.5); print round(-2.5); print pow(2, 10); print pow(2, -1);"),
+        "4\n3\n2.5\n2\n3\n3\n-3\n1024\n0.5\n"
+    );
+}
+
+#[test]
+fn math_natives_reject_bad_input() {
+    assert_eq!(run_err("sqrt(-4);"), "sqrt() of negative number -4.");
+    assert_eq!(run_err("floor(\"x\");"), "floor() expects a number.");
+    assert_eq!(run_err("pow(2, nil);"), "pow() expects two numbers.");
+}
+
+#[test]
+fn curry_binds_leading_arguments() {
+    assert_eq!(
+        run("fun add(a, b) { return a + b; } var add5 = curry(add, 5); print add5(3); print arity(add5);"),
+        "8\n1\n"
+    );
+}
+
+#[test]
+fn curry_can_be_chained() {
+    assert_eq!(
+        run("fun sum3(a, b, c) { return a * 100 + b * 10 + c; } var f = curry(curry(sum3, 1), 2); print f(3); print arity(f);"),
+        "123\n1\n"
+    );
+}
+
+#[test]
+fn curry_rejects_non_functions_and_nullary_functions() {
+    assert_eq!(run_err("curry(1, 2);"), "curry() expects a function.");
+    assert_eq!(run_err("fun f() {} curry(f, 1);"), "curry() expects a function that takes at least one argument.");
+}
+
+#[test]
+fn group_digits_inserts_thousands_separators() {
+    assert_eq!(
+        run("print group_digits(-1234567.5); print group_digits(1000); print group_digits(999); print group_digits(0);"),
+        "-1,234,567.5\n1,000\n999\n0\n"
+    );
+}
+
+#[test]
+fn group_digits_rejects_non_numbers() {
+    assert_eq!(run_err("group_digits(\"1000\");"), "group_digits() expects a number.");
+}
+
+#[test]
+fn arity_reports_parameter_counts() {
+    assert_eq!(
+        run("fun add(a, b) { return a + b; } print arity(clock); print arity(add); print arity(fun (x) { return x; }); print arity(env);"),
+        "0\n2\n1\n1\n"
+    );
+}
+
+#[test]
+fn arity_rejects_non_functions() {
+    assert_eq!(run_err("arity(1);"), "arity() expects a function.");
+}
+
+#[test]
+fn flatten_removes_one_level_of_nesting() {
+    assert_eq!(run("print flatten([1, [2, 3], [], [[4]]]);"), "[1, 2, 3, [4]]\n");
+}
+
+#[test]
+fn flat_map_concatenates_the_results() {
+    assert_eq!(
+        run("fun pair(x) { return [x, x * 10]; } print flat_map([1, 2], pair); print flat_map([1, 2], fun (x) { return x; });"),
+        "[1, 10, 2, 20]\n[1, 2]\n"
+    );
+}
+
+#[test]
+fn flatten_and_flat_map_reject_non_lists() {
+    assert_eq!(run_err("flatten(1);"), "flatten() expects a list.");
+    assert_eq!(run_err("flat_map(1, clock);"), "flat_map() expects a list and a function.");
+}
+
+#[test]
+fn to_array_splits_strings_into_characters() {
+    assert_eq!(run("print to_array(\"héy\"); print len(to_array(\"\"));"), "[h, é, y]\n0\n");
+}
+
+#[test]
+fn to_array_returns_lists_unchanged() {
+    assert_eq!(run("var l = [1, 2]; print to_array(l) == l;"), "true\n");
+}
+
+#[test]
+fn to_array_rejects_other_values() {
+    assert_eq!(run_err("to_array(1);"), "to_array() expects a string or list.");
+}
+
+#[test]
+fn range_supports_one_and_two_bounds() {
+    assert_eq!(run("print range(4); print range(2, 5); print range(0.5, 3);"), "[0, 1, 2, 3]\n[2, 3, 4]\n[0.5, 1.5, 2.5]\n");
+}
+
+#[test]
+fn range_is_empty_when_start_is_not_below_end() {
+    assert_eq!(run("print range(0); print range(5, 2); print len(range(-3));"), "[]\n[]\n0\n");
+}
+
+#[test]
+fn range_drives_for_in_loops() {
+    assert_eq!(run("var sum = 0; for (var i in range(1, 5)) sum = sum + i; print sum;"), "10\n");
+}
+
+#[test]
+fn range_rejects_bad_arguments() {
+    assert_eq!(run_err("range(\"3\");"), "range() expects one or two numbers.");
+    assert_eq!(run_err("range(0, 1e999);"), "range() expects finite numbers.");
+    assert_eq!(run_err("range(1, 2, 3);"), "[line 1] Function 'range' expected between 1 and 2 arguments but got 3.");
+}
+
+/// 创建一个使用模拟时钟的解释器：`sleep` 不会真正等待，而是把时钟向前拨动。
+fn interpreter_with_mock_clock() -> (Interpreter, SharedBuffer) {
+    let (mut interpreter, out) = interpreter();
+    let now = Rc::new(RefCell::new(Duration::from_secs(1_000)));
+    let clock = now.clone();
+    interpreter.set_clock(move || *clock.borrow());
+    interpreter.set_sleeper(move |duration| *now.borrow_mut() += duration);
+    (interpreter, out)
+}
+
+#[test]
+fn bench_reports_average_milliseconds_per_call() {
+    let (mut interpreter, out) = interpreter_with_mock_clock();
+    exec(&mut interpreter, "var calls = 0; fun work() { calls = calls + 1; sleep(calls * 10); } print bench(work, 4); print calls;").unwrap();
+    // 四次调用共耗时 10 + 20 + 30 + 40 = 100 毫秒
+    assert_eq!(out.contents(), "25\n4\n");
+}
+
+#[test]
+fn bench_propagates_errors_from_the_function() {
+    let (mut interpreter, _) = interpreter_with_mock_clock();
+    assert_eq!(exec(&mut interpreter, "fun bad() { return 1 / 0; }\nbench(bad, 3);").unwrap_err(), "[line 1] Division by zero is not allowed.");
+}
+
+#[test]
+fn bench_validates_its_arguments() {
+    assert_eq!(run_err("fun f() {} bench(f, 0);"), "bench() expects a positive integer iteration count.");
+    assert_eq!(run_err("fun f() {} bench(f, 1.5);"), "bench() expects a positive integer iteration count.");
+    assert_eq!(run_err("fun f(x) {} bench(f, 1);"), "bench() expects a function that takes no arguments.");
+}
+
+#[test]
+fn callbacks_with_the_wrong_arity_report_the_native_call_line() {
+    assert_eq!(
+        run_err("fun add(a, b) { return a + b; }\nflat_map([1], add);"),
+        "[line 2] Expected 2 arguments but got 1."
+    );
+    assert_eq!(
+        run_err("fun none() { return true; }\nvar x = 1;\npartition([1], none);"),
+        "[line 3] Expected 0 arguments but got 1."
+    );
+    assert_eq!(run_err("fun msg(x) { return x; }\nassert(false, msg);"), "[line 2] Expected 1 arguments but got 0.");
+}
+
+#[test]
+fn callback_arity_errors_use_the_innermost_native_call() {
+    let src = "fun add(a, b) { return a + b; }
+        fun outer(x) {
+            return flat_map([x], add);
+        }
+        print flat_map([1], outer);";
+    assert_eq!(run_err(src), "[line 3] Expected 2 arguments but got 1.");
+}
+
+#[test]
+fn min_and_max_accept_any_number_of_arguments() {
+    assert_eq!(run("print max(...[1, 5, 3]); print min(4, 2, 8); print max(7); print min(1, 2.5);"), "5\n2\n7\n1\n");
+    assert_eq!(run("print max(1, 2.5, 2);"), "2.5\n");
+}
+
+#[test]
+fn min_and_max_reject_non_numbers() {
+    assert_eq!(run_err("max(1, \"2\");"), "max() expects numbers.");
+    assert_eq!(run_err("min(\"a\");"), "min() expects numbers.");
+    assert_eq!(run_err("max(...[]);"), "[line 1] Function 'max' expected at least 1 arguments but got 0.");
+}
+
+#[test]
+fn spread_expands_into_fixed_arity_functions() {
+    assert_eq!(run("fun add3(a, b, c) { return a + b + c; } print add3(...[1, 2, 3]); print add3(1, ...[2, 3]);"), "6\n6\n");
+    assert_eq!(run_err("fun add3(a, b, c) {}\nadd3(...[1, 2]);"), "[line 2] Expected 3 arguments but got 2.");
+    assert_eq!(run_err("print max(...5);"), "[line 1] Can only spread a list but got 5 (number).");
+}
+
+#[test]
+fn assert_calls_a_message_function_only_when_it_fails() {
+    let src = "var calls = 0;
+        fun message() { calls = calls + 1; return \"built\"; }
+        assert(true, message);
+        print calls;";
+    assert_eq!(run(src), "0\n");
+    let src = "var calls = 0;
+        fun message() { calls = calls + 1; return \"built \" + str(calls); }
+        assert(1 > 2, message);";
+    assert_eq!(run_err(src), "assert() failed: built 1");
+}
+
+#[test]
+fn assert_keeps_plain_messages_and_the_bare_form() {
+    assert_eq!(run_err("assert(false, \"plain\");"), "assert() failed: plain");
+    assert_eq!(run_err("assert(nil);"), "assert() failed.");
+    assert_eq!(run_err("assert(false, fun() { return [1, 2]; });"), "assert() failed: [1, 2]");
+}
+
+#[test]
+fn take_and_drop_split_a_list() {
+    assert_eq!(run("print take([1, 2, 3, 4], 2); print drop([1, 2, 3, 4], 2);"), "[1, 2]\n[3, 4]\n");
+    assert_eq!(run("print take([1, 2], 0); print drop([1, 2], 0);"), "[]\n[1, 2]\n");
+}
+
+#[test]
+fn take_and_drop_clamp_the_count_to_the_length() {
+    assert_eq!(run("print take([1, 2, 3], 5); print drop([1, 2, 3], 5); print take([], 1);"), "[1, 2, 3]\n[]\n[]\n");
+}
+
+#[test]
+fn take_and_drop_return_new_lists() {
+    assert_eq!(run("var a = [1, 2, 3]; var t = take(a, 3); t[0] = 9; var d = drop(a, 0); d[1] = 8; print a;"), "[1, 2, 3]\n");
+}
+
+#[test]
+fn take_and_drop_reject_bad_arguments() {
+    assert_eq!(run_err("take([1], -1);"), "take() expects a list and a non-negative whole number.");
+    assert_eq!(run_err("drop([1], 1.5);"), "drop() expects a list and a non-negative whole number.");
+    assert_eq!(run_err("take(\"ab\", 1);"), "take() expects a list and a non-negative whole number.");
+}
@@ -0,0 +1,151 @@
+
+use codecrafters_interpreter::{Parser, Scanner};
+
+/// 把单个表达式解析成 AST 的文本形式。
+fn parse_expr(src: &str) -> String {
+    let (tokens, _) = Scanner::new(src).scan_tokens_with_diagnostics();
+    let (expr, had_error) = Parser::new(tokens).parse_expr();
+    assert!(!had_error, "parse error in {:?}", src);
+    expr.unwrap().to_string_expr()
+}
+
+#[test]
+fn min_max_assign_parses_as_dedicated_node() {
+    assert_eq!(parse_expr("x min= 5"), "(min= x 5.0)");
+    assert_eq!(parse_expr("a[0] max= y"), "(max= (index a 0.0) y)");
+}
+
+#[test]
+fn min_and_max_remain_ordinary_identifiers() {
+    assert_eq!(parse_expr("min == max"), "(== min max)");
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    assert_eq!(parse_expr("a or b and c"), "(a or (b and c))");
+    assert_eq!(parse_expr("a and b or c"), "((a and b) or c)");
+}
+
+#[test]
+fn logical_operators_are_left_associative() {
+    assert_eq!(parse_expr("a or b or c"), "((a or b) or c)");
+    assert_eq!(parse_expr("a and b and c"), "((a and b) and c)");
+}
+
+#[test]
+fn trailing_block_becomes_last_argument() {
+    assert_eq!(parse_expr("repeat(3) { print \"hi\"; }"), "(call repeat 3.0 (lambda ()))");
+    assert_eq!(parse_expr("run() { }"), "(call run (lambda ()))");
+}
+
+fn parse_errors(src: &str) -> Vec<String> {
+    let (tokens, _) = Scanner::new(src).scan_tokens_with_diagnostics();
+    match Parser::new(tokens).parse_with_diagnostics() {
+        Ok(_) => Vec::new(),
+        Err(diagnostics) => diagnostics.iter().map(|d| d.to_string()).collect(),
+    }
+}
+
+#[test]
+fn function_declaration_requires_name_and_balanced_parens() {
+    assert_eq!(parse_errors("fun greet(n) { print n; }"), Vec::<String>::new());
+    assert_eq!(parse_errors("fun zero() {}"), Vec::<String>::new());
+    assert_eq!(parse_errors("fun greet(a, b { }"), vec!["[line 1] Error at '{': Expect ')' after parameters."]);
+    assert_eq!(parse_errors("fun greet(a b) {}"), vec!["[line 1] Error at 'b': Expect ')' after parameters."]);
+    assert_eq!(parse_errors("fun 1() {}"), vec!["[line 1] Error at '1': Expect '(' after 'fun'."]);
+}
+
+#[test]
+fn return_statement_requires_a_terminator() {
+    assert_eq!(parse_errors("fun f() { return; }"), Vec::<String>::new());
+    assert_eq!(parse_errors("fun f() { return 1 }")[0], "[line 1] Error at '}': Expect ';' after return value.");
+}
+
+#[test]
+fn equality_operators_chain_left_associatively() {
+    assert_eq!(parse_expr("a == b == c"), "(== (== a b) c)");
+    assert_eq!(parse_expr("a != b == c"), "(== (!= a b) c)");
+}
+
+#[test]
+fn ternary_prints_as_a_conditional_node() {
+    assert_eq!(parse_expr("c ? a : b"), "(? c a b)");
+    assert_eq!(parse_expr("a ? b : c ? d : e"), "(? a b (? c d e))");
+    assert_eq!(parse_expr("x or y ? 1 : 2"), "(? (x or y) 1.0 2.0)");
+}
+
+fn parse_errors_asi(src: &str) -> Vec<String> {
+    let (tokens, _) = Scanner::new(src).scan_tokens_with_diagnostics();
+    let mut parser = Parser::new(tokens);
+    parser.set_asi(true);
+    match parser.parse_with_diagnostics() {
+        Ok(_) => Vec::new(),
+        Err(diagnostics) => diagnostics.iter().map(|d| d.to_string()).collect(),
+    }
+}
+
+#[test]
+fn asi_lets_newlines_end_statements() {
+    assert_eq!(parse_errors_asi("print 1\nprint 2\nvar x = 3"), Vec::<String>::new());
+    assert_eq!(parse_errors("print 1\nprint 2")[0], "[line 2] Error at 'print': Expect ';' after value.");
+}
+
+/// 在 ASI 模式下解析源码，返回顶层语句的个数。
+fn statement_count_asi(src: &str) -> usize {
+    let (tokens, _) = Scanner::new(src).scan_tokens_with_diagnostics();
+    let mut parser = Parser::new(tokens);
+    parser.set_asi(true);
+    parser.parse_with_diagnostics().unwrap().len()
+}
+
+#[test]
+fn asi_does_not_join_a_min_or_max_assignment_onto_the_previous_line() {
+    assert_eq!(statement_count_asi("var max = 0\nprint x\nmax = 5\nprint max"), 4);
+    assert_eq!(statement_count_asi("var x = 1\nx min= 0"), 2);
+}
+
+#[test]
+fn asi_still_requires_semicolons_within_a_line() {
+    assert_eq!(parse_errors_asi("print 1 print 2")[0], "[line 1] Error at 'print': Expect ';' after value.");
+}
+
+#[test]
+fn if_expression_requires_an_else_branch() {
+    assert_eq!(parse_errors("var x = if (true) 1;"), vec!["[line 1] Error at ';': Expect 'else' branch in if expression."]);
+}
+
+#[test]
+fn lambda_prints_its_parameters() {
+    assert_eq!(parse_expr("fun (a, b) { return a; }"), "(lambda (a b))");
+    assert_eq!(parse_expr("fun () {}"), "(lambda ())");
+}
+
+#[test]
+fn parser_recovers_and_reports_independent_errors() {
+    assert_eq!(
+        parse_errors("var = 1;\nprint 2;\nif (true print 3;\nvar ok = 4;"),
+        vec![
+            "[line 1] Error at '=': Expect variable name.",
+            "[line 3] Error at 'print': Expect ')' after 'the condition of if statement'.",
+        ]
+    );
+}
+
+#[test]
+fn synchronization_stops_at_statement_keywords() {
+    // 第二行缺少分号，恢复后从下一行的 `fun` 重新开始解析，不会产生连带错误
+    assert_eq!(
+        parse_errors("print 1 + ;\nvar x = 2\nfun f() {}\nwhile (true) print;"),
+        vec![
+            "[line 1] Error at ';': Expect expression.",
+            "[line 3] Error at 'fun': Expect ';' after variable declaration.",
+            "[line 4] Error at ';': Expect expression.",
+        ]
+    );
+}
+
+#[test]
+fn rest_parameter_must_be_last() {
+    assert_eq!(parse_errors("fun f(a, ...rest) {}"), Vec::<String>::new());
+    assert_eq!(parse_errors("fun f(...rest, b) {}"), vec!["[line 1] Error at ',': Rest parameter must be the last parameter."]);
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use codecrafters_interpreter::repl::run_repl_with;
+use common::{interpreter, SharedBuffer};
+
+/// 把 `input` 逐行交给 REPL，返回标准输出和错误输出的内容。
+fn repl(input: &str) -> (String, String) {
+    let (mut interpreter, out) = interpreter();
+    let err = SharedBuffer::default();
+    interpreter.set_error_output(err.clone());
+    run_repl_with(&mut interpreter, input.as_bytes());
+    (out.contents(), err.contents())
+}
+
+#[test]
+fn bare_expression_prints_value_and_type() {
+    assert_eq!(repl("1 + 2\n"), ("> => 3 : number\n> \n".to_string(), String::new()));
+}
+
+#[test]
+fn statements_print_nothing() {
+    assert_eq!(repl("var x = 1;\n"), ("> > \n".to_string(), String::new()));
+}
+
+#[test]
+fn annotation_uses_the_value_type() {
+    let (out, _) = repl("\"hi\"\nnil\n[1]\n");
+    assert_eq!(out, "> => hi : string\n> => nil : nil\n> => [1] : list\n> \n");
+}
+
+#[test]
+fn variables_and_functions_persist_between_lines() {
+    let (out, err) = repl("var x = 1;\nfun inc(n) { return n + 1; }\ninc(x)\n");
+    assert_eq!(out, "> > > => 2 : number\n> \n");
+    assert_eq!(err, "");
+}
+
+#[test]
+fn errors_are_reported_and_the_loop_continues() {
+    let (out, err) = repl("print y;\nvar = 1;\nprint 3;\n");
+    assert_eq!(out, "> > > 3\n> \n");
+    assert_eq!(err, "Runtime error: [line 1] Undefined variable 'y'.\n[line 1] Error at '=': Expect variable name.\n");
+}
+
+#[test]
+fn a_global_from_an_earlier_line_may_be_redeclared_from_itself() {
+    let (out, _) = repl("var n = 1\nvar n = n + 1\nn\n");
+    assert!(out.contains("=> 2 : number"), "{}", out);
+}
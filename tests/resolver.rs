@@ -0,0 +1,51 @@
+use codecrafters_interpreter::ast::Expr;
+use codecrafters_interpreter::resolver::Resolver;
+use codecrafters_interpreter::{Parser, Scanner};
+
+/// 开启递归警告解析源码，返回全部警告信息。
+fn recursion_warnings(src: &str) -> Vec<String> {
+    let (tokens, _) = Scanner::new(src).scan_tokens_with_diagnostics();
+    let stmts = Parser::new(tokens).parse_with_diagnostics().unwrap();
+    let mut resolver = Resolver::new();
+    resolver.set_warn_recursion(true);
+    resolver.resolve(&stmts).unwrap();
+    resolver.warnings().iter().map(|w| w.to_string()).collect()
+}
+
+#[test]
+fn tail_recursion_does_not_warn() {
+    let src = "fun count(n, acc) {
+            if (n == 0) return acc;
+            return count(n - 1, acc + 1);
+        }";
+    assert_eq!(recursion_warnings(src), Vec::<String>::new());
+}
+
+#[test]
+fn non_tail_recursion_warns_at_the_call() {
+    let src = "fun sum(n) {
+            if (n == 0) return 0;
+            return n + sum(n - 1);
+        }";
+    assert_eq!(recursion_warnings(src), vec!["[line 3] Warning: non-tail recursive call may overflow."]);
+}
+
+#[test]
+fn recursion_warnings_are_off_by_default() {
+    let (tokens, _) = Scanner::new("fun f(n) { return 1 + f(n); }").scan_tokens_with_diagnostics();
+    let stmts = Parser::new(tokens).parse_with_diagnostics().unwrap();
+    let mut resolver = Resolver::new();
+    resolver.resolve(&stmts).unwrap();
+    assert!(resolver.warnings().is_empty());
+}
+
+#[test]
+fn spread_outside_call_arguments_is_a_resolve_error() {
+    let (tokens, _) = Scanner::new("f(...xs)").scan_tokens_with_diagnostics();
+    let spread = match Parser::new(tokens).parse_expr().0 {
+        Some(Expr::Call { mut arguments, .. }) => arguments.remove(0),
+        _ => panic!("expected a call"),
+    };
+    let errors: Vec<String> = Resolver::new().resolve_expression(&spread).unwrap_err().iter().map(|d| d.to_string()).collect();
+    assert_eq!(errors, vec!["[line 1] Error at '...': Spread is only allowed in call arguments."]);
+}
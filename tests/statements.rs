@@ -0,0 +1,617 @@
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use codecrafters_interpreter::callable::Callable;
+use codecrafters_interpreter::{Interpreter, MskValue, RuntimeError};
+use common::{exec, interpreter, run, run_err};
+
+/// 依次返回预先给定的各行，读完后返回 nil，用来代替 `read_line`。
+struct FakeLines(RefCell<Vec<&'static str>>);
+
+impl Callable for FakeLines {
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn call(&self, _interpreter: &mut Interpreter, _args: Vec<MskValue>) -> Result<MskValue, RuntimeError> {
+        let mut lines = self.0.borrow_mut();
+        if lines.is_empty() {
+            return Ok(MskValue::Nil);
+        }
+        Ok(MskValue::String(lines.remove(0).to_string()))
+    }
+}
+
+#[test]
+fn while_body_variables_do_not_leak_across_iterations() {
+    let src = "var i = 0;
+        while (i < 3) {
+            var seen;
+            print seen;
+            seen = i;
+            i = i + 1;
+        }";
+    assert_eq!(run(src), "nil\nnil\nnil\n");
+}
+
+#[test]
+fn while_body_without_block_gets_a_fresh_scope() {
+    assert_eq!(run_err("var i = 0;\nwhile (i < 1) var leaked = i = i + 1;\nprint leaked;"), "[line 3] Undefined variable 'leaked'.");
+}
+
+#[test]
+fn continue_in_while_re_evaluates_the_condition() {
+    let src = "var i = 0;
+        while (i < 5) {
+            i = i + 1;
+            if (i % 2 == 0) continue;
+            print i;
+        }";
+    assert_eq!(run(src), "1\n3\n5\n");
+}
+
+#[test]
+fn match_runs_the_first_matching_literal_arm() {
+    let src = "fun describe(n) { match n { 1 => return \"one\"; -1 => return \"minus one\"; _ => return \"other\"; } }
+        print describe(1); print describe(-1); print describe(7);";
+    assert_eq!(run(src), "one\nminus one\nother\n");
+}
+
+#[test]
+fn match_binding_captures_the_subject() {
+    assert_eq!(run("match 41 { 0 => print \"zero\"; n => print n + 1; }"), "42\n");
+}
+
+#[test]
+fn match_list_pattern_binds_elements() {
+    assert_eq!(run("match [1, 2] { [0, x] => print \"no\"; [1, x] => print x; }"), "2\n");
+}
+
+#[test]
+fn match_list_pattern_requires_same_length_and_a_list() {
+    let src = "fun shape(v) { match v { [] => return \"empty\"; [_] => return \"one\"; [a, [b, c]] => return a + b + c; _ => return \"other\"; } }
+        print shape([]); print shape([9]); print shape([1, [2, 3]]); print shape([1, 2, 3]); print shape(\"[]\");";
+    assert_eq!(run(src), "empty\none\n6\nother\nother\n");
+}
+
+#[test]
+fn match_list_pattern_bindings_are_scoped_to_the_arm() {
+    assert_eq!(run_err("match [1] { [x] => print x; }\nprint x;"), "[line 2] Undefined variable 'x'.");
+}
+
+#[test]
+fn using_closes_the_resource_after_the_body() {
+    let src = format!("{}using (var r = Resource(\"a\")) {{ print \"use \" + r.name; }} print \"after\";", RESOURCE);
+    assert_eq!(run(&src), "use a\nclose a\nafter\n");
+}
+
+#[test]
+fn using_closes_the_resource_when_the_body_fails() {
+    let src = format!("{}using (var r = Resource(\"a\")) {{ print \"start\"; print 1 / 0; print \"unreachable\"; }}", RESOURCE);
+    let (mut interpreter, out) = interpreter();
+    let err = exec(&mut interpreter, &src).unwrap_err();
+    assert_eq!(err, "[line 5] Division by zero is not allowed.");
+    assert_eq!(out.contents(), "start\nclose a\n");
+}
+
+#[test]
+fn using_closes_the_resource_on_return_and_break() {
+    let src = format!(
+        "{}fun f() {{ using (var r = Resource(\"f\")) {{ return 1; }} }}
+        print f();
+        while (true) {{ using (var r = Resource(\"loop\")) {{ break; }} }}",
+        RESOURCE
+    );
+    assert_eq!(run(&src), "close f\n1\nclose loop\n");
+}
+
+#[test]
+fn using_nested_resources_close_in_reverse_order() {
+    let src = format!("{}using (var a = Resource(\"outer\")) using (var b = Resource(\"inner\")) print \"body\";", RESOURCE);
+    assert_eq!(run(&src), "body\nclose inner\nclose outer\n");
+}
+
+#[test]
+fn using_requires_a_close_method() {
+    assert_eq!(
+        run_err("class Plain {}\nusing (var p = Plain()) print \"body\";"),
+        "[line 2] Resource 'p' must have a close() method but got Plain instance (instance)."
+    );
+}
+
+#[test]
+fn using_binding_is_scoped_to_the_statement() {
+    let src = format!("{}using (var r = Resource(\"a\")) {{}}\nprint r;", RESOURCE);
+    assert_eq!(run_err(&src), "[line 6] Undefined variable 'r'.");
+}
+
+#[test]
+fn labeled_break_leaves_the_labeled_block_with_a_value() {
+    let src = "var x = outer: { for (var i = 0; i < 10; i = i + 1) { if (i == 3) break outer i * 10; } 0; };
+        print x;";
+    assert_eq!(run(src), "30\n");
+}
+
+#[test]
+fn labeled_break_cannot_cross_a_function_boundary() {
+    let src = "var x = outer: {\n  fun f() { break outer 1; }\n  f();\n};";
+    assert_eq!(run_err(src), "[line 2] Error at 'outer': No enclosing block labeled 'outer'.");
+}
+
+#[test]
+fn break_with_unknown_label_is_rejected() {
+    assert_eq!(run_err("break nolabel 3;"), "[line 1] Error at 'nolabel': No enclosing block labeled 'nolabel'.");
+}
+
+#[test]
+fn break_and_continue_outside_a_loop_are_rejected() {
+    assert_eq!(run_err("break;"), "[line 1] Error at 'break': Can't use 'break' outside of a loop.");
+    assert_eq!(
+        run_err("while (true) {\n  fun f() { continue; }\n}"),
+        "[line 2] Error at 'continue': Can't use 'continue' outside of a loop."
+    );
+}
+
+#[test]
+fn infinite_while_loop_stops_at_the_iteration_limit() {
+    let (mut interpreter, _) = interpreter();
+    interpreter.set_max_iterations(100);
+    let err = exec(&mut interpreter, "var i = 0;\nwhile (true) {\n  i = i + 1;\n}").unwrap_err();
+    assert_eq!(err, "[line 2] Loop exceeded maximum iterations.");
+}
+
+#[test]
+fn iteration_limit_applies_to_each_loop_separately() {
+    let (mut interpreter, out) = interpreter();
+    interpreter.set_max_iterations(3);
+    exec(&mut interpreter, "for (var i = 0; i < 3; i = i + 1) {} for (var j = 0; j < 3; j = j + 1) {} print \"done\";").unwrap();
+    assert_eq!(out.contents(), "done\n");
+    let err = exec(&mut interpreter, "\n\nfor (;;) {}").unwrap_err();
+    assert_eq!(err, "[line 3] Loop exceeded maximum iterations.");
+}
+
+#[test]
+fn elif_chain_runs_exactly_one_branch() {
+    let src = "fun sign(n) { if (n < 0) return \"negative\"; elif (n == 0) return \"zero\"; else return \"positive\"; }
+        print sign(-3); print sign(0); print sign(8);";
+    assert_eq!(run(src), "negative\nzero\npositive\n");
+}
+
+#[test]
+fn elif_and_else_if_can_be_mixed() {
+    let src = "var n = 2; if (n == 0) print \"a\"; elif (n == 1) print \"b\"; else if (n == 2) print \"c\"; elif (n == 3) print \"d\";";
+    assert_eq!(run(src), "c\n");
+}
+
+#[test]
+fn trailing_block_is_passed_as_a_function() {
+    let src = "fun repeat(n, body) { for (var i = 0; i < n; i = i + 1) body(); }
+        repeat(3) { print \"hi\"; }
+        print \"done\";";
+    assert_eq!(run(src), "hi\nhi\nhi\ndone\n");
+}
+
+#[test]
+fn trailing_block_closes_over_surrounding_variables() {
+    let src = "fun twice(f) { f(); f(); }
+        var count = 0;
+        twice() { count = count + 1; };
+        print count;";
+    assert_eq!(run(src), "2\n");
+}
+
+#[test]
+fn match_subject_is_not_a_trailing_block_call() {
+    assert_eq!(run("fun id(x) { return x; } match id(1) { 1 => print \"one\"; }"), "one\n");
+}
+
+#[test]
+fn function_declarations_run_with_and_without_parameters() {
+    assert_eq!(run("fun greet(n) { print n; } fun hello() { greet(\"hi\"); } hello();"), "hi\n");
+}
+
+#[test]
+fn return_without_value_returns_nil() {
+    assert_eq!(run("fun f() { print \"in\"; return; print \"unreachable\"; } print f();"), "in\nnil\n");
+}
+
+#[test]
+fn return_value_is_passed_to_the_caller() {
+    assert_eq!(run("fun add(a, b) { return a + b; } print add(2, 3);"), "5\n");
+}
+
+#[test]
+fn while_binding_runs_once_per_line_until_nil() {
+    let (mut interpreter, out) = interpreter();
+    interpreter.register("next_line", Rc::new(FakeLines(RefCell::new(vec!["a", "b", "c"]))));
+    exec(&mut interpreter, "var n = 0; while (var line = next_line()) { n = n + 1; print line; } print n;").unwrap();
+    assert_eq!(out.contents(), "a\nb\nc\n3\n");
+}
+
+#[test]
+fn while_binding_is_scoped_to_the_loop() {
+    assert_eq!(
+        run_err("var i = 0;\nwhile (var x = i < 1) { i = i + 1; }\nprint x;"),
+        "[line 3] Undefined variable 'x'."
+    );
+}
+
+#[test]
+fn return_inside_a_loop_leaves_the_function() {
+    let src = "fun first_over(list, limit) { for (var x in list) { if (x > limit) return x; } return nil; }
+        print first_over([1, 5, 9], 3); print first_over([1], 3);";
+    assert_eq!(run(src), "5\nnil\n");
+}
+
+#[test]
+fn return_inside_nested_ifs_skips_the_remaining_statements() {
+    let src = "fun classify(n) { if (n > 0) { if (n > 10) { return \"big\"; } print \"small\"; } return \"done\"; }
+        print classify(20); print classify(5);";
+    assert_eq!(run(src), "big\nsmall\ndone\n");
+}
+
+#[test]
+fn continue_in_match_arm_continues_the_enclosing_loop() {
+    let src = "for (var i = 0; i < 4; i = i + 1) { match i % 2 { 0 => continue; _ => print \"odd\"; } print i; }";
+    assert_eq!(run(src), "odd\n1\nodd\n3\n");
+}
+
+#[test]
+fn break_in_match_arm_breaks_the_enclosing_loop() {
+    let src = "var i = 0; while (true) { match i { 2 => break; _ => print i; } i = i + 1; } print \"after\";";
+    assert_eq!(run(src), "0\n1\nafter\n");
+}
+
+#[test]
+fn return_with_several_values_packs_them_into_a_list() {
+    assert_eq!(
+        run("fun divmod(a, b) { return a / b, a % b; } var q; var r; (q, r) = divmod(7, 2); print q; print r; print divmod(9, 3);"),
+        "3.5\n1\n[3, 0]\n"
+    );
+}
+
+#[test]
+fn return_with_a_single_value_is_not_wrapped() {
+    assert_eq!(run("fun one() { return 1; } print one(); print type(one());"), "1\nnumber\n");
+}
+
+#[test]
+fn make_counter_closures_keep_separate_state() {
+    let src = "fun makeCounter() {
+            var i = 0;
+            fun count() { i = i + 1; return i; }
+            return count;
+        }
+        var c1 = makeCounter();
+        var c2 = makeCounter();
+        print c1(); print c1(); print c2();";
+    assert_eq!(run(src), "1\n2\n1\n");
+}
+
+#[test]
+fn closures_are_not_affected_by_later_shadowing() {
+    let src = "var a = \"global\";
+        {
+            fun show() { print a; }
+            show();
+            var a = \"block\";
+            show();
+        }";
+    assert_eq!(run(src), "global\nglobal\n");
+}
+
+#[test]
+fn parallel_assignment_in_a_closure_uses_resolved_bindings() {
+    let src = "var a = 0; var b = 0;
+        {
+            fun set() { (a, b) = (1, 2); }
+            var a = \"local\";
+            set();
+            print a;
+        }
+        print a; print b;";
+    assert_eq!(run(src), "local\n1\n2\n");
+}
+
+#[test]
+fn variables_cannot_be_read_in_their_own_initializer() {
+    let message = "Error at 'a': Can't read local variable in its own initializer.";
+    assert_eq!(run_err("var a = a;"), format!("[line 1] {}", message));
+    assert_eq!(run_err("var a = 1;\n{\n  var a = a + 1;\n}"), format!("[line 3] {}", message));
+}
+
+#[test]
+fn a_global_may_be_redeclared_from_its_previous_value() {
+    assert_eq!(run("var a = 1; var a = a + 1; print a;"), "2\n");
+    assert_eq!(run("fun f() { return 1; } var f = f() + 1; print f;"), "2\n");
+    assert_eq!(run("var len = len(\"abc\"); print len;"), "3\n");
+    assert_eq!(run_err("var a = 1; var b = b;"), "[line 1] Error at 'b': Can't read local variable in its own initializer.");
+}
+
+#[test]
+fn a_global_from_an_earlier_run_may_be_redeclared_from_itself() {
+    let (mut interpreter, out) = interpreter();
+    exec(&mut interpreter, "var count = 1;").unwrap();
+    exec(&mut interpreter, "var count = count + 1; print count;").unwrap();
+    assert_eq!(out.contents(), "2\n");
+}
+
+#[test]
+fn functions_in_an_initializer_may_refer_to_the_variable() {
+    assert_eq!(
+        run("var fact = fun (n) { if (n <= 1) return 1; return n * fact(n - 1); }; print fact(5);"),
+        "120\n"
+    );
+}
+
+#[test]
+fn lambdas_can_be_stored_and_invoked_immediately() {
+    assert_eq!(
+        run("var f = fun (x) { return x * 2; }; print f(21); print (fun (x) { return x; })(5); print fun () {};"),
+        "42\n5\n<fn>\n"
+    );
+}
+
+#[test]
+fn lambdas_capture_the_enclosing_scope() {
+    assert_eq!(
+        run("fun adder(n) { return fun (x) { return x + n; }; } var add3 = adder(3); print add3(4);"),
+        "7\n"
+    );
+}
+
+const POINT: &str = "class Point {
+        init(x, y) { this.x = x; this.y = y; }
+        sum() { return this.x + this.y; }
+    }
+";
+
+#[test]
+fn calling_a_class_runs_init_and_creates_an_instance() {
+    let src = format!("{}var p = Point(1, 2); print Point; print p; print p.x; print p.sum();", POINT);
+    assert_eq!(run(&src), "Point\nPoint instance\n1\n3\n");
+}
+
+#[test]
+fn fields_can_be_set_and_added_on_instances() {
+    let src = format!("{}var p = Point(1, 2); p.x = 10; p.label = \"moved\"; print p.sum(); print p.label;", POINT);
+    assert_eq!(run(&src), "12\nmoved\n");
+}
+
+#[test]
+fn property_errors_report_their_line() {
+    assert_eq!(run_err(&format!("{}var p = Point(1, 2);\nprint p.missing;", POINT)), "[line 6] Undefined property 'missing'.");
+    assert_eq!(run_err("var n = 1;\nn.x = 2;"), "[line 2] Only instances and classes have fields.");
+    assert_eq!(run_err(&format!("{}Point(1);", POINT)), "[line 5] Expected 2 arguments but got 1.");
+}
+
+#[test]
+fn methods_read_and_write_fields_through_this() {
+    let src = "class Counter {
+            init() { this.count = 0; }
+            bump() { this.count = this.count + 1; return this; }
+        }
+        var c = Counter();
+        c.bump().bump();
+        print c.count;";
+    assert_eq!(run(src), "2\n");
+}
+
+#[test]
+fn bound_methods_remember_their_instance() {
+    let src = "class Greeter {
+            init(name) { this.name = name; }
+            greet() { return \"hi \" + this.name; }
+        }
+        var greet = Greeter(\"ann\").greet;
+        print greet();";
+    assert_eq!(run(src), "hi ann\n");
+}
+
+#[test]
+fn this_outside_a_class_is_a_resolve_error() {
+    assert_eq!(run_err("print this;"), "[line 1] Error at 'this': Can't use 'this' outside of a class.");
+    assert_eq!(run_err("fun f() { return this; }"), "[line 1] Error at 'this': Can't use 'this' outside of a class.");
+}
+
+#[test]
+fn subclasses_override_and_inherit_methods() {
+    let src = "class A { hi() { return \"A\"; } name() { return \"a:\" + this.hi(); } }
+        class B < A { hi() { return \"B\"; } }
+        print B().hi(); print B().name(); print A().name();";
+    assert_eq!(run(src), "B\na:B\na:A\n");
+}
+
+#[test]
+fn super_calls_the_superclass_method_on_this() {
+    let src = "class A { init(x) { this.x = x; } describe() { return \"x=\" + str(this.x); } }
+        class B < A {
+            init(x, y) { super.init(x); this.y = y; }
+            describe() { return super.describe() + \", y=\" + str(this.y); }
+        }
+        print B(1, 2).describe();";
+    assert_eq!(run(src), "x=1, y=2\n");
+}
+
+#[test]
+fn super_outside_a_subclass_is_a_resolve_error() {
+    assert_eq!(run_err("fun f() { return super.x(); }"), "[line 1] Error at 'super': Can't use 'super' outside of a class.");
+    assert_eq!(
+        run_err("class A { m() { return super.m(); } }"),
+        "[line 1] Error at 'super': Can't use 'super' in a class with no superclass."
+    );
+}
+
+#[test]
+fn superclass_must_be_a_class() {
+    assert_eq!(run_err("var NotClass = 1;\nclass C < NotClass {}"), "[line 2] Superclass must be a class.");
+}
+
+const VECTOR: &str = "class Vector {
+        init(x, y) { this.x = x; this.y = y; }
+        add(other) { return Vector(this.x + other.x, this.y + other.y); }
+        equals(other) { return this.x == other.x and this.y == other.y; }
+        compare(other) { return (this.x + this.y) - (other.x + other.y); }
+    }
+";
+
+#[test]
+fn plus_dispatches_to_add() {
+    let src = format!("{}var v = Vector(1, 2) + Vector(3, 4); print v.x; print v.y;", VECTOR);
+    assert_eq!(run(&src), "4\n6\n");
+}
+
+#[test]
+fn equality_and_comparison_dispatch_to_magic_methods() {
+    let src = format!(
+        "{}var a = Vector(1, 2); var b = Vector(1, 2); var c = Vector(5, 5);
+        print a == b; print a != b; print a < c; print c <= a; print c > a;",
+        VECTOR
+    );
+    assert_eq!(run(&src), "true\nfalse\ntrue\nfalse\ntrue\n");
+}
+
+#[test]
+fn instances_without_magic_methods_keep_the_usual_errors() {
+    assert_eq!(
+        run_err("class P {}\nprint P() + 1;"),
+        "[line 2] '+' expected two numbers or two strings but got P instance (instance) and 1 (number)."
+    );
+    assert_eq!(run("class P {} var p = P(); print p == p; print p == P();"), "true\nfalse\n");
+}
+
+#[test]
+fn compare_must_return_a_number() {
+    assert_eq!(
+        run_err("class P { compare(other) { return \"x\"; } }\nprint P() < P();"),
+        "[line 2] compare() must return a number but got x (string)."
+    );
+}
+
+#[test]
+fn for_in_iterates_list_elements() {
+    assert_eq!(run("var total = 0; for (var x in [1, 2, 3]) { total = total + x; print x; } print total;"), "1\n2\n3\n6\n");
+}
+
+#[test]
+fn for_in_iterates_string_characters() {
+    assert_eq!(run("for (var c in \"héy\") print c;"), "h\né\ny\n");
+}
+
+#[test]
+fn for_in_honors_break_and_continue() {
+    assert_eq!(
+        run("for (var x in [1, 2, 3, 4, 5]) { if (x == 2) continue; if (x == 4) break; print x; }"),
+        "1\n3\n"
+    );
+}
+
+#[test]
+fn for_in_gives_each_iteration_its_own_binding() {
+    let src = "var first;
+        for (var x in [1, 2]) { if (first == nil) first = fun () { return x; }; }
+        print first();";
+    assert_eq!(run(src), "1\n");
+}
+
+#[test]
+fn for_in_with_two_names_binds_index_and_element() {
+    assert_eq!(run("for (var i, x in [\"a\", \"b\"]) print str(i) + x;"), "0a\n1b\n");
+    assert_eq!(run("for (var i, c in \"hé\") print str(i) + c;"), "0h\n1é\n");
+}
+
+#[test]
+fn for_in_index_is_local_to_each_iteration() {
+    let src = "var i = \"outer\"; var last;
+        for (var i, x in [1, 2]) { last = fun () { return i; }; }
+        print i; print last();";
+    assert_eq!(run(src), "outer\n1\n");
+}
+
+#[test]
+fn for_in_rejects_non_iterables() {
+    assert_eq!(run_err("for (var x in 5) print x;"), "[line 1] for-in expected a list or string but got 5 (number).");
+}
+
+const RESOURCE: &str = "class Resource {
+    init(name) { this.name = name; }
+    close() { print \"close \" + this.name; }
+}
+";
+
+#[test]
+fn user_function_arity_is_checked_once_at_the_call_site() {
+    assert_eq!(run_err("fun f(a, b) {}\nf(1);"), "[line 2] Expected 2 arguments but got 1.");
+    assert_eq!(run_err("fun f(a) {}\nf(1, 2);"), "[line 2] Expected 1 arguments but got 2.");
+}
+
+#[test]
+fn magic_methods_with_the_wrong_arity_report_the_operator_line() {
+    assert_eq!(run_err("class P { add() { return 1; } }\nprint P() + 1;"), "[line 2] Expected 0 arguments but got 1.");
+}
+
+#[test]
+fn rest_parameter_collects_extra_arguments() {
+    let src = "fun log(level, ...args) { print level; print args; print len(args); }
+        log(\"info\", 1, 2, 3);
+        log(\"debug\");";
+    assert_eq!(run(src), "info\n[1, 2, 3]\n3\ndebug\n[]\n0\n");
+}
+
+#[test]
+fn rest_parameter_still_requires_the_fixed_parameters() {
+    assert_eq!(
+        run_err("fun log(level, ...args) {}\nlog();"),
+        "[line 2] Function 'log' expected at least 1 arguments but got 0."
+    );
+    assert_eq!(run("fun f(...xs) { return xs; } print f(); print arity(f);"), "[]\n0\n");
+}
+
+#[test]
+fn max_call_depth_is_configurable() {
+    let (mut interpreter, out) = interpreter();
+    interpreter.set_max_call_depth(10);
+    let src = "fun depth(n) { if (n == 0) return 0; return 1 + depth(n - 1); }";
+    exec(&mut interpreter, &format!("{} print depth(9);", src)).unwrap();
+    assert_eq!(out.contents(), "9\n");
+    assert_eq!(
+        exec(&mut interpreter, &format!("{} print depth(10);", src)).unwrap_err(),
+        "Stack overflow: maximum call depth exceeded."
+    );
+}
+
+const COUNTER: &str = "class Counter {
+    static count = 0;
+    static next() { Counter.count = Counter.count + 1; return Counter.count; }
+}";
+
+#[test]
+fn static_methods_are_called_on_the_class() {
+    assert_eq!(run(&format!("{}\nprint Counter.next(); print Counter.next(); print Counter.count;", COUNTER)), "1\n2\n2\n");
+}
+
+#[test]
+fn static_members_are_not_visible_on_instances() {
+    assert_eq!(run_err(&format!("{}\nprint Counter().next();", COUNTER)), "[line 5] Undefined property 'next'.");
+    assert_eq!(run_err("class A {}\nprint A.missing;"), "[line 2] Undefined static member 'missing'.");
+}
+
+#[test]
+fn subclasses_inherit_static_members() {
+    assert_eq!(run(&format!("{}\nclass Sub < Counter {{}}\nprint Sub.next(); print Counter.count;", COUNTER)), "1\n1\n");
+}
+
+#[test]
+fn static_field_initializers_can_refer_to_the_class() {
+    assert_eq!(run("class Node { static root = Node; static() { return \"method\"; } }\nprint Node.root == Node; print Node().static();"), "true\nmethod\n");
+}
+
+#[test]
+fn this_in_a_static_method_is_a_resolve_error() {
+    assert_eq!(
+        run_err("class A { static f() { return this; } }"),
+        "[line 1] Error at 'this': Can't use 'this' in a static member."
+    );
+}
@@ -0,0 +1,127 @@
+use codecrafters_interpreter::Scanner;
+
+fn tokens_json(src: &str) -> String {
+    let (tokens, had_error) = Scanner::new(src).scan_tokens();
+    assert!(!had_error, "unexpected scan error in {:?}", src);
+    let json: Vec<String> = tokens.iter().map(|token| token.to_json()).collect();
+    format!("[{}]", json.join(","))
+}
+
+/// 按 `tokenize` 命令的格式输出全部 Token，每行一个。
+fn tokenize(src: &str) -> String {
+    let (tokens, had_error) = Scanner::new(src).scan_tokens();
+    assert!(!had_error, "unexpected scan error in {:?}", src);
+    tokens.iter().map(|token| format!("{}\n", token)).collect()
+}
+
+#[test]
+fn elif_is_a_keyword_token() {
+    assert_eq!(tokenize("elif else if elif_x"), "ELIF elif null\nELSE else null\nIF if null\nIDENTIFIER elif_x null\nEOF  null\n");
+}
+
+#[test]
+fn to_json_matches_golden_output() {
+    assert_eq!(
+        tokens_json("var s = \"a\\\"b\";\nprint 1.5;"),
+        concat!(
+            "[",
+            r#"{"type":"VAR","lexeme":"var","literal":null,"line":1,"column":1},"#,
+            r#"{"type":"IDENTIFIER","lexeme":"s","literal":null,"line":1,"column":5},"#,
+            r#"{"type":"EQUAL","lexeme":"=","literal":null,"line":1,"column":7},"#,
+            r#"{"type":"STRING","lexeme":"\"a\\\"b\"","literal":"a\"b","line":1,"column":9},"#,
+            r#"{"type":"SEMICOLON","lexeme":";","literal":null,"line":1,"column":15},"#,
+            r#"{"type":"PRINT","lexeme":"print","literal":null,"line":2,"column":1},"#,
+            r#"{"type":"NUMBER","lexeme":"1.5","literal":1.5,"line":2,"column":7},"#,
+            r#"{"type":"SEMICOLON","lexeme":";","literal":null,"line":2,"column":10},"#,
+            r#"{"type":"EOF","lexeme":"","literal":null,"line":2,"column":11}"#,
+            "]"
+        )
+    );
+}
+
+#[test]
+fn to_json_writes_integer_literals_as_floats() {
+    assert_eq!(
+        tokens_json("42"),
+        r#"[{"type":"NUMBER","lexeme":"42","literal":42.0,"line":1,"column":1},{"type":"EOF","lexeme":"","literal":null,"line":1,"column":3}]"#
+    );
+}
+
+#[test]
+fn to_json_writes_null_for_non_finite_numbers() {
+    assert_eq!(
+        tokens_json("1e999"),
+        r#"[{"type":"NUMBER","lexeme":"1e999","literal":null,"line":1,"column":1},{"type":"EOF","lexeme":"","literal":null,"line":1,"column":6}]"#
+    );
+}
+
+#[test]
+fn block_comments_span_lines_and_nest() {
+    assert_eq!(tokenize("a /* one\ntwo /* nested */ still\n*/ b"), "IDENTIFIER a null\nIDENTIFIER b null\nEOF  null\n");
+}
+
+#[test]
+fn tokens_after_a_block_comment_keep_their_line() {
+    let (tokens, _) = Scanner::new("/*\n\n*/ x").scan_tokens();
+    assert_eq!((tokens[0].line, tokens[0].column), (3, 4));
+}
+
+#[test]
+fn unterminated_block_comment_reports_its_starting_line() {
+    let (_, diagnostics) = Scanner::new("x\n/* open\n/* inner */\n").scan_tokens_with_diagnostics();
+    let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+    assert_eq!(messages, vec!["[line 2] Error: Unterminated block comment."]);
+}
+
+#[test]
+fn comment_markers_inside_strings_are_literal_text() {
+    assert_eq!(tokenize("\"a /* b\""), "STRING \"a /* b\" a /* b\nEOF  null\n");
+    assert_eq!(tokenize("\"*/\" x"), "STRING \"*/\" */\nIDENTIFIER x null\nEOF  null\n");
+}
+
+#[test]
+fn quotes_inside_block_comments_do_not_start_strings() {
+    assert_eq!(tokenize("/* \"unterminated */ x"), "IDENTIFIER x null\nEOF  null\n");
+}
+
+#[test]
+fn numbers_accept_an_exponent() {
+    assert_eq!(
+        tokenize("1e3 1.5E2 2e-2 4e+1"),
+        "NUMBER 1e3 1000.0\nNUMBER 1.5E2 150.0\nNUMBER 2e-2 0.02\nNUMBER 4e+1 40.0\nEOF  null\n"
+    );
+}
+
+#[test]
+fn an_e_without_digits_is_not_an_exponent() {
+    assert_eq!(tokenize("2e"), "NUMBER 2 2.0\nIDENTIFIER e null\nEOF  null\n");
+    assert_eq!(tokenize("3e-x"), "NUMBER 3 3.0\nIDENTIFIER e null\nMINUS - null\nIDENTIFIER x null\nEOF  null\n");
+    assert_eq!(tokenize("1.e5"), "NUMBER 1 1.0\nDOT . null\nIDENTIFIER e5 null\nEOF  null\n");
+}
+
+/// 扫描源码并返回全部扫描错误的文本。
+fn scan_errors(src: &str) -> Vec<String> {
+    let (_, diagnostics) = Scanner::new(src).scan_tokens_with_diagnostics();
+    diagnostics.iter().map(|d| d.to_string()).collect()
+}
+
+#[test]
+fn underscores_separate_digits() {
+    assert_eq!(
+        tokenize("1_000_000 3.141_592 1e1_0"),
+        "NUMBER 1_000_000 1000000.0\nNUMBER 3.141_592 3.141592\nNUMBER 1e1_0 10000000000.0\nEOF  null\n"
+    );
+}
+
+#[test]
+fn misplaced_underscores_are_scan_errors() {
+    assert_eq!(scan_errors("1_"), vec!["[line 1] Error: Invalid digit separator in number '1_'."]);
+    assert_eq!(scan_errors("1__0"), vec!["[line 1] Error: Invalid digit separator in number '1__0'."]);
+    assert_eq!(scan_errors("1_.5"), vec!["[line 1] Error: Invalid digit separator in number '1_.5'."]);
+    assert_eq!(scan_errors("1_e2"), vec!["[line 1] Error: Invalid digit separator in number '1_e2'."]);
+}
+
+#[test]
+fn a_leading_underscore_starts_an_identifier() {
+    assert_eq!(tokenize("_1"), "IDENTIFIER _1 null\nEOF  null\n");
+}